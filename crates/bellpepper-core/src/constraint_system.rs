@@ -102,6 +102,36 @@ pub trait ConstraintSystem<Scalar: PrimeField>: Sized + Send {
         LB: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
         LC: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>;
 
+    /// Enforce that `A` * `B` = `C` for already built linear combinations.
+    /// This is a convenience wrapper around `enforce`.
+    fn enforce_lc<A, AR>(
+        &mut self,
+        annotation: A,
+        a: LinearCombination<Scalar>,
+        b: LinearCombination<Scalar>,
+        c: LinearCombination<Scalar>,
+    ) where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.enforce(annotation, |_| a, |_| b, |_| c)
+    }
+
+    /// Enforce that `A` * `B` = `C`, labelling the constraint with the
+    /// category `tag`. Constraint systems which support it can then check
+    /// the constraints of one category on their own; by default the tag is
+    /// ignored and this is the same as `enforce`.
+    fn enforce_tagged<A, AR, LA, LB, LC>(&mut self, _tag: &str, annotation: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+        LB: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+        LC: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+    {
+        self.enforce(annotation, a, b, c)
+    }
+
     /// Create a new (sub)namespace and enter into it. Not intended
     /// for downstream use; use `namespace` instead.
     fn push_namespace<NR, N>(&mut self, name_fn: N)
@@ -128,6 +158,52 @@ pub trait ConstraintSystem<Scalar: PrimeField>: Sized + Send {
         Namespace(self.get_root(), Default::default())
     }
 
+    /// Runs `f` inside a new namespace called `name`. The namespace is
+    /// popped when `f` returns, or if it panics.
+    fn with_namespace<R, F>(&mut self, name: &str, f: F) -> R
+    where
+        F: FnOnce(&mut Self) -> R,
+    {
+        self.get_root().push_namespace(|| name);
+
+        let guard = NamespaceGuard(self, PhantomData);
+        f(&mut *guard.0)
+    }
+
+    /// Returns the number of constraints enforced so far.
+    ///
+    /// Defaults to 0, which is right for witness generators as they do not
+    /// record constraints. Implementations which record constraints must
+    /// override this.
+    fn num_constraints(&self) -> usize {
+        0
+    }
+
+    /// Returns the number of public inputs allocated so far, including the
+    /// "one" input.
+    ///
+    /// Witness generators count their inputs slice by default; for other
+    /// implementations the default is 0, so those which allocate inputs
+    /// must override this.
+    fn num_inputs(&self) -> usize {
+        if self.is_witness_generator() {
+            return self.inputs_slice().len();
+        }
+        0
+    }
+
+    /// Returns the number of private (auxiliary) variables allocated so far.
+    ///
+    /// Witness generators count their aux slice by default; for other
+    /// implementations the default is 0, so those which allocate auxiliary
+    /// variables must override this.
+    fn num_aux(&self) -> usize {
+        if self.is_witness_generator() {
+            return self.aux_slice().len();
+        }
+        0
+    }
+
     /// Most implementations of ConstraintSystem are not 'extensible': they won't implement a specialized
     /// version of `extend` and should therefore also keep the default implementation of `is_extensible`
     /// so callers which optionally make use of `extend` can know to avoid relying on it when unimplemented.
@@ -282,6 +358,17 @@ impl<'cs, Scalar: PrimeField, CS: ConstraintSystem<Scalar>> ConstraintSystem<Sca
         self.0.enforce(annotation, a, b, c)
     }
 
+    fn enforce_tagged<A, AR, LA, LB, LC>(&mut self, tag: &str, annotation: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+        LB: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+        LC: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+    {
+        self.0.enforce_tagged(tag, annotation, a, b, c)
+    }
+
     // Downstream users who use `namespace` will never interact with these
     // functions and they will never be invoked because the namespace is
     // never a root constraint system.
@@ -302,6 +389,18 @@ impl<'cs, Scalar: PrimeField, CS: ConstraintSystem<Scalar>> ConstraintSystem<Sca
         self.0.get_root()
     }
 
+    fn num_constraints(&self) -> usize {
+        self.0.num_constraints()
+    }
+
+    fn num_inputs(&self) -> usize {
+        self.0.num_inputs()
+    }
+
+    fn num_aux(&self) -> usize {
+        self.0.num_aux()
+    }
+
     fn is_witness_generator(&self) -> bool {
         self.0.is_witness_generator()
     }
@@ -332,6 +431,19 @@ impl<'a, Scalar: PrimeField, CS: ConstraintSystem<Scalar>> Drop for Namespace<'a
     }
 }
 
+/// Pops the namespace entered by `ConstraintSystem::with_namespace` when
+/// dropped.
+struct NamespaceGuard<'a, Scalar: PrimeField, CS: ConstraintSystem<Scalar>>(
+    &'a mut CS,
+    PhantomData<Scalar>,
+);
+
+impl<'a, Scalar: PrimeField, CS: ConstraintSystem<Scalar>> Drop for NamespaceGuard<'a, Scalar, CS> {
+    fn drop(&mut self) {
+        self.0.get_root().pop_namespace()
+    }
+}
+
 /// Convenience implementation of ConstraintSystem<Scalar> for mutable references to
 /// constraint systems.
 impl<'cs, Scalar: PrimeField, CS: ConstraintSystem<Scalar>> ConstraintSystem<Scalar>
@@ -372,6 +484,17 @@ impl<'cs, Scalar: PrimeField, CS: ConstraintSystem<Scalar>> ConstraintSystem<Sca
         (**self).enforce(annotation, a, b, c)
     }
 
+    fn enforce_tagged<A, AR, LA, LB, LC>(&mut self, tag: &str, annotation: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+        LB: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+        LC: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+    {
+        (**self).enforce_tagged(tag, annotation, a, b, c)
+    }
+
     fn push_namespace<NR, N>(&mut self, name_fn: N)
     where
         NR: Into<String>,
@@ -396,6 +519,18 @@ impl<'cs, Scalar: PrimeField, CS: ConstraintSystem<Scalar>> ConstraintSystem<Sca
         (**self).namespace(name_fn)
     }
 
+    fn num_constraints(&self) -> usize {
+        (**self).num_constraints()
+    }
+
+    fn num_inputs(&self) -> usize {
+        (**self).num_inputs()
+    }
+
+    fn num_aux(&self) -> usize {
+        (**self).num_aux()
+    }
+
     fn is_extensible() -> bool {
         CS::is_extensible()
     }
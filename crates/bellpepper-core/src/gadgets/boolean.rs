@@ -21,6 +21,12 @@ impl AllocatedBit {
         self.variable
     }
 
+    /// Wraps an already allocated variable as a bit. The caller is
+    /// responsible for constraining `variable` to be 0 or 1.
+    pub(crate) fn new_unchecked(variable: Variable, value: Option<bool>) -> Self {
+        AllocatedBit { variable, value }
+    }
+
     /// Allocate a variable in the constraint system which can only be a
     /// boolean value. Further, constrain that the boolean is false
     /// unless the condition is false.
@@ -272,6 +278,30 @@ impl AllocatedBit {
     }
 }
 
+/// Reorders little-endian bits (`bits[i]` has weight `2^i`) into the
+/// big-endian byte encoding of the same number, with the bits of each byte
+/// most significant first, i.e. the bit string read off the bytes from left
+/// to right. The input is first padded with `Boolean::constant(false)` up
+/// to a multiple of 8 bits. This adds no constraints.
+pub fn le_bits_to_be_bytes(bits: &[Boolean]) -> Vec<Boolean> {
+    let padded_len = (bits.len() + 7) / 8 * 8;
+
+    let mut res = bits.to_vec();
+    res.resize(padded_len, Boolean::constant(false));
+    res.reverse();
+    res
+}
+
+/// The inverse of `le_bits_to_be_bytes`: reorders the bits of a big-endian
+/// byte encoding (bits of each byte most significant first) into
+/// little-endian bits. The padding added by `le_bits_to_be_bytes` is kept.
+/// Panics if `bits.len()` is not a multiple of 8.
+pub fn be_bytes_to_le_bits(bits: &[Boolean]) -> Vec<Boolean> {
+    assert_eq!(bits.len() % 8, 0);
+
+    bits.iter().rev().copied().collect()
+}
+
 pub fn u64_into_boolean_vec_le<Scalar: PrimeField, CS: ConstraintSystem<Scalar>>(
     mut cs: CS,
     value: Option<u64>,
@@ -426,6 +456,156 @@ impl Boolean {
         }
     }
 
+    /// Enforces that exactly one of `bits` is set, with the single linear
+    /// constraint `sum(bits) = 1`. An empty slice is unsatisfiable.
+    pub fn enforce_exactly_one<Scalar, CS>(mut cs: CS, bits: &[Self]) -> Result<(), SynthesisError>
+    where
+        Scalar: PrimeField,
+        CS: ConstraintSystem<Scalar>,
+    {
+        if bits.is_empty() {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        let sum = Self::sum_lc::<Scalar, CS>(bits);
+        cs.enforce(
+            || "exactly one constraint",
+            |lc| lc,
+            |lc| lc,
+            |lc| lc + CS::one() - &sum,
+        );
+
+        Ok(())
+    }
+
+    /// Enforces that at most one of `bits` is set. As the bits are boolean,
+    /// their sum `s` lies in `[0, bits.len()]`, so the single constraint
+    /// `s * (s - 1) = 0` restricts it to 0 or 1.
+    pub fn enforce_at_most_one<Scalar, CS>(mut cs: CS, bits: &[Self]) -> Result<(), SynthesisError>
+    where
+        Scalar: PrimeField,
+        CS: ConstraintSystem<Scalar>,
+    {
+        let sum = Self::sum_lc::<Scalar, CS>(bits);
+        cs.enforce(
+            || "at most one constraint",
+            |lc| lc + &sum,
+            |lc| lc + &sum - CS::one(),
+            |lc| lc,
+        );
+
+        Ok(())
+    }
+
+    /// Enforces that `value_bits` is a submask of `allowed_bits`: every bit
+    /// at a position where `allowed_bits` is false must be false. As the
+    /// bits are boolean, all of them are pinned by the single constraint
+    /// that their sum is zero. A constant true bit at a forbidden position
+    /// is unsatisfiable.
+    pub fn enforce_mask_subset<Scalar, CS>(
+        mut cs: CS,
+        value_bits: &[Self],
+        allowed_bits: &[bool],
+    ) -> Result<(), SynthesisError>
+    where
+        Scalar: PrimeField,
+        CS: ConstraintSystem<Scalar>,
+    {
+        if value_bits.len() != allowed_bits.len() {
+            return Err(SynthesisError::IncompatibleLengthVector(format!(
+                "mask has {} bits but allowed mask has {}",
+                value_bits.len(),
+                allowed_bits.len()
+            )));
+        }
+
+        let mut forbidden = vec![];
+        for (bit, allowed) in value_bits.iter().zip(allowed_bits) {
+            match (bit, allowed) {
+                (_, true) | (Boolean::Constant(false), false) => {}
+                (Boolean::Constant(true), false) => return Err(SynthesisError::Unsatisfiable),
+                (Boolean::Is(_) | Boolean::Not(_), false) => forbidden.push(*bit),
+            }
+        }
+
+        if !forbidden.is_empty() {
+            let sum = Self::sum_lc::<Scalar, CS>(&forbidden);
+            cs.enforce(|| "mask subset constraint", |lc| lc, |lc| lc, |_| sum);
+        }
+
+        Ok(())
+    }
+
+    /// Enforces that `bits`, read as little-endian bytes of 8 bits each,
+    /// match `bytes` wherever the expected byte is `Some`; `None` leaves the
+    /// byte free. Each pinned byte costs a single linear constraint packing
+    /// its non-constant bits, and constant bits are checked directly, so a
+    /// constant mismatch is unsatisfiable.
+    pub fn enforce_bytes_match<Scalar, CS>(
+        mut cs: CS,
+        bits: &[Self],
+        bytes: &[Option<u8>],
+    ) -> Result<(), SynthesisError>
+    where
+        Scalar: PrimeField,
+        CS: ConstraintSystem<Scalar>,
+    {
+        if bits.len() != bytes.len() * 8 {
+            return Err(SynthesisError::IncompatibleLengthVector(format!(
+                "{} bits but {} bytes",
+                bits.len(),
+                bytes.len()
+            )));
+        }
+
+        for (i, (byte_bits, byte)) in bits.chunks(8).zip(bytes).enumerate() {
+            let Some(byte) = byte else {
+                continue;
+            };
+
+            let mut packed = LinearCombination::zero();
+            let mut expected = 0u8;
+            let mut has_variables = false;
+            let mut coeff = Scalar::ONE;
+            for (j, bit) in byte_bits.iter().enumerate() {
+                let expected_bit = (byte >> j) & 1 == 1;
+                match bit {
+                    Boolean::Constant(b) if *b != expected_bit => {
+                        return Err(SynthesisError::Unsatisfiable)
+                    }
+                    Boolean::Constant(_) => {}
+                    Boolean::Is(_) | Boolean::Not(_) => {
+                        packed = packed + &bit.lc(CS::one(), coeff);
+                        expected |= u8::from(expected_bit) << j;
+                        has_variables = true;
+                    }
+                }
+                coeff = coeff.double();
+            }
+
+            if has_variables {
+                cs.enforce(
+                    || format!("byte {} constraint", i),
+                    |_| packed,
+                    |lc| lc + CS::one(),
+                    |lc| lc + (Scalar::from(u64::from(expected)), CS::one()),
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn sum_lc<Scalar, CS>(bits: &[Self]) -> LinearCombination<Scalar>
+    where
+        Scalar: PrimeField,
+        CS: ConstraintSystem<Scalar>,
+    {
+        bits.iter().fold(LinearCombination::zero(), |acc, bit| {
+            acc + &bit.lc(CS::one(), Scalar::ONE)
+        })
+    }
+
     pub fn get_value(&self) -> Option<bool> {
         match *self {
             Boolean::Constant(c) => Some(c),
@@ -532,6 +712,32 @@ impl Boolean {
         )?))
     }
 
+    /// Perform implication `a => b`, i.e. `(not a) or b`
+    pub fn implies<'a, Scalar, CS>(
+        cs: CS,
+        a: &'a Boolean,
+        b: &'a Boolean,
+    ) -> Result<Boolean, SynthesisError>
+    where
+        Scalar: PrimeField,
+        CS: ConstraintSystem<Scalar>,
+    {
+        Boolean::or(cs, &a.not(), b)
+    }
+
+    /// Perform biconditional `a <=> b`, i.e. `not (a xor b)`
+    pub fn iff<'a, Scalar, CS>(
+        cs: CS,
+        a: &'a Boolean,
+        b: &'a Boolean,
+    ) -> Result<Boolean, SynthesisError>
+    where
+        Scalar: PrimeField,
+        CS: ConstraintSystem<Scalar>,
+    {
+        Ok(Boolean::xor(cs, a, b)?.not())
+    }
+
     /// Computes (a and b) xor ((not a) and c)
     pub fn sha256_ch<'a, Scalar, CS>(
         mut cs: CS,
@@ -757,6 +963,31 @@ impl Boolean {
         }
         .into())
     }
+
+    /// Returns `(b, a)` if `cond` is true and `(a, b)` otherwise. A
+    /// constant `cond` is folded and costs no constraints.
+    pub fn conditionally_swap<Scalar, CS>(
+        mut cs: CS,
+        a: &Self,
+        b: &Self,
+        cond: &Self,
+    ) -> Result<(Self, Self), SynthesisError>
+    where
+        Scalar: PrimeField,
+        CS: ConstraintSystem<Scalar>,
+    {
+        match cond {
+            Boolean::Constant(false) => return Ok((*a, *b)),
+            Boolean::Constant(true) => return Ok((*b, *a)),
+            Boolean::Is(_) | Boolean::Not(_) => {}
+        }
+
+        // (cond and x) xor ((not cond) and y) selects x if cond, y otherwise
+        let first = Self::sha256_ch(cs.namespace(|| "first"), cond, b, a)?;
+        let second = Self::sha256_ch(cs.namespace(|| "second"), cond, a, b)?;
+
+        Ok((first, second))
+    }
 }
 
 impl From<AllocatedBit> for Boolean {
@@ -767,9 +998,12 @@ impl From<AllocatedBit> for Boolean {
 
 #[cfg(test)]
 mod test {
-    use super::{field_into_allocated_bits_le, u64_into_boolean_vec_le, AllocatedBit, Boolean};
+    use super::{
+        be_bytes_to_le_bits, field_into_allocated_bits_le, le_bits_to_be_bytes,
+        u64_into_boolean_vec_le, AllocatedBit, Boolean,
+    };
     use crate::test_cs::*;
-    use crate::ConstraintSystem;
+    use crate::{ConstraintSystem, SynthesisError};
     use blstrs::Scalar as Fr;
     use ff::{Field, PrimeField};
 
@@ -1024,6 +1258,161 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_enforce_exactly_one() {
+        // The first bit is optionally a constant, which enters the sums as a
+        // term of `CS::one()`.
+        fn alloc_bits(
+            cs: &mut TestConstraintSystem<Fr>,
+            values: &[bool],
+            constant: bool,
+        ) -> Vec<Boolean> {
+            values
+                .iter()
+                .enumerate()
+                .map(|(i, v)| {
+                    if constant && i == 0 {
+                        Boolean::constant(*v)
+                    } else {
+                        Boolean::from(
+                            AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), Some(*v))
+                                .unwrap(),
+                        )
+                    }
+                })
+                .collect()
+        }
+
+        let cases: [&[bool]; 6] = [
+            &[false, true, false, false],
+            &[true],
+            &[false, false, false],
+            &[true, false, true],
+            &[true, true, true],
+            &[],
+        ];
+
+        for values in cases {
+            let set = values.iter().filter(|v| **v).count();
+
+            for constant in [false, true] {
+                let mut cs = TestConstraintSystem::<Fr>::new();
+                let bits = alloc_bits(&mut cs, values, constant);
+                let res = Boolean::enforce_exactly_one(cs.namespace(|| "exactly one"), &bits);
+                if values.is_empty() {
+                    assert!(matches!(res, Err(SynthesisError::Unsatisfiable)));
+                } else {
+                    res.unwrap();
+                    assert_eq!(cs.is_satisfied(), set == 1);
+                }
+
+                let mut cs = TestConstraintSystem::<Fr>::new();
+                let bits = alloc_bits(&mut cs, values, constant);
+                Boolean::enforce_at_most_one(cs.namespace(|| "at most one"), &bits).unwrap();
+                assert_eq!(cs.is_satisfied(), set <= 1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_enforce_mask_subset() {
+        let allowed = [true, false, true, false, false];
+
+        for (values, is_subset) in [
+            ([true, false, true, false, false], true),
+            ([false, false, true, false, false], true),
+            ([true, false, true, true, false], false),
+            ([false, true, false, false, false], false),
+        ] {
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let bits = values
+                .iter()
+                .enumerate()
+                .map(|(i, v)| {
+                    // Mix in negated bits, which carry a constant term.
+                    let negated = i % 2 == 0;
+                    let bit = AllocatedBit::alloc(
+                        cs.namespace(|| format!("bit {}", i)),
+                        Some(*v ^ negated),
+                    )
+                    .unwrap();
+                    if negated {
+                        Boolean::from(bit).not()
+                    } else {
+                        Boolean::from(bit)
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            let constraints = cs.num_constraints();
+            Boolean::enforce_mask_subset(cs.namespace(|| "subset"), &bits, &allowed).unwrap();
+
+            assert_eq!(cs.num_constraints(), constraints + 1);
+            assert_eq!(cs.is_satisfied(), is_subset);
+            if !is_subset {
+                assert_eq!(
+                    cs.which_is_unsatisfied(),
+                    Some("subset/mask subset constraint")
+                );
+            }
+        }
+
+        // Constant bits are checked directly.
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let bits = [Boolean::constant(true), Boolean::constant(false)];
+        Boolean::enforce_mask_subset(cs.namespace(|| "constant"), &bits, &[true, false]).unwrap();
+        assert_eq!(cs.num_constraints(), 0);
+        assert!(matches!(
+            Boolean::enforce_mask_subset(cs.namespace(|| "violating"), &bits, &[false, false]),
+            Err(SynthesisError::Unsatisfiable)
+        ));
+        assert!(matches!(
+            Boolean::enforce_mask_subset(cs.namespace(|| "length"), &bits, &[false]),
+            Err(SynthesisError::IncompatibleLengthVector(_))
+        ));
+    }
+
+    #[test]
+    fn test_enforce_bytes_match() {
+        // Little-endian bytes 0xca, 0xfe, 0x42.
+        let value = 0x42feca;
+        let header = [Some(0xca), Some(0xfe), None];
+
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let bits = u64_into_boolean_vec_le(cs.namespace(|| "value"), Some(value)).unwrap();
+        let constraints = cs.num_constraints();
+
+        Boolean::enforce_bytes_match(cs.namespace(|| "header"), &bits[..24], &header).unwrap();
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints() - constraints, 2);
+
+        cs.set("value/bit 9/boolean", Fr::ZERO);
+        assert_eq!(cs.which_is_unsatisfied(), Some("header/byte 1 constraint"));
+
+        // The free byte may hold anything.
+        cs.set("value/bit 9/boolean", Fr::ONE);
+        cs.set("value/bit 16/boolean", Fr::ONE);
+        assert!(cs.is_satisfied());
+
+        // Constant bits are checked directly.
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let mut bits = (0..8)
+            .map(|i| Boolean::constant((0xca >> i) & 1 == 1))
+            .collect::<Vec<_>>();
+        Boolean::enforce_bytes_match(cs.namespace(|| "constant"), &bits, &[Some(0xca)]).unwrap();
+        assert_eq!(cs.num_constraints(), 0);
+        bits[0] = Boolean::constant(true);
+        assert!(matches!(
+            Boolean::enforce_bytes_match(cs.namespace(|| "mismatch"), &bits, &[Some(0xca)]),
+            Err(SynthesisError::Unsatisfiable)
+        ));
+
+        assert!(matches!(
+            Boolean::enforce_bytes_match(cs.namespace(|| "length"), &bits[..7], &[Some(0xca)]),
+            Err(SynthesisError::IncompatibleLengthVector(_))
+        ));
+    }
+
     #[test]
     fn test_boolean_negation() {
         let mut cs = TestConstraintSystem::<Fr>::new();
@@ -1771,6 +2160,72 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_boolean_implies_iff() {
+        let variants = [
+            OperandType::True,
+            OperandType::False,
+            OperandType::AllocatedTrue,
+            OperandType::AllocatedFalse,
+            OperandType::NegatedAllocatedTrue,
+            OperandType::NegatedAllocatedFalse,
+        ];
+
+        for first_operand in variants.iter().cloned() {
+            for second_operand in variants.iter().cloned() {
+                let mut cs = TestConstraintSystem::<Fr>::new();
+
+                let a;
+                let b;
+
+                {
+                    let mut dyn_construct = |operand, name| {
+                        let cs = cs.namespace(|| name);
+
+                        match operand {
+                            OperandType::True => Boolean::constant(true),
+                            OperandType::False => Boolean::constant(false),
+                            OperandType::AllocatedTrue => {
+                                Boolean::from(AllocatedBit::alloc(cs, Some(true)).unwrap())
+                            }
+                            OperandType::AllocatedFalse => {
+                                Boolean::from(AllocatedBit::alloc(cs, Some(false)).unwrap())
+                            }
+                            OperandType::NegatedAllocatedTrue => {
+                                Boolean::from(AllocatedBit::alloc(cs, Some(true)).unwrap()).not()
+                            }
+                            OperandType::NegatedAllocatedFalse => {
+                                Boolean::from(AllocatedBit::alloc(cs, Some(false)).unwrap()).not()
+                            }
+                        }
+                    };
+
+                    a = dyn_construct(first_operand, "a");
+                    b = dyn_construct(second_operand, "b");
+                }
+
+                let implies = Boolean::implies(cs.namespace(|| "implies"), &a, &b).unwrap();
+                let iff = Boolean::iff(cs.namespace(|| "iff"), &a, &b).unwrap();
+
+                assert!(cs.is_satisfied());
+                assert_eq!(
+                    implies.get_value().unwrap(),
+                    !first_operand.val() || second_operand.val()
+                );
+                assert_eq!(
+                    iff.get_value().unwrap(),
+                    first_operand.val() == second_operand.val()
+                );
+
+                if first_operand.is_constant() && second_operand.is_constant() {
+                    assert!(matches!(implies, Boolean::Constant(_)));
+                    assert!(matches!(iff, Boolean::Constant(_)));
+                    assert_eq!(cs.num_constraints(), 0);
+                }
+            }
+        }
+    }
+
     #[allow(clippy::identity_op)]
     #[test]
     fn test_u64_into_boolean_vec_le() {
@@ -1793,6 +2248,43 @@ mod test {
         assert!(!bits[63 - 22].get_value().unwrap());
     }
 
+    #[test]
+    fn test_le_bits_to_be_bytes() {
+        let value = 0b1_0110_1001_1100u64;
+        let len = 13;
+        let le_bits = (0..len)
+            .map(|i| Boolean::constant((value >> i) & 1 == 1))
+            .collect::<Vec<_>>();
+
+        let be_bytes = le_bits_to_be_bytes(&le_bits);
+        assert_eq!(be_bytes.len(), 16);
+
+        let expected = value.to_be_bytes()[6..]
+            .iter()
+            .flat_map(|byte| (0..8).rev().map(move |i| (byte >> i) & 1 == 1))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            be_bytes
+                .iter()
+                .map(|b| b.get_value().unwrap())
+                .collect::<Vec<_>>(),
+            expected
+        );
+
+        let round_trip = be_bytes_to_le_bits(&be_bytes);
+        assert_eq!(round_trip.len(), 16);
+        for (i, bit) in round_trip.iter().enumerate() {
+            assert_eq!(bit.get_value().unwrap(), (value >> i) & 1 == 1);
+        }
+        assert_eq!(
+            le_bits_to_be_bytes(&round_trip)
+                .iter()
+                .map(|b| b.get_value().unwrap())
+                .collect::<Vec<_>>(),
+            expected
+        );
+    }
+
     #[allow(clippy::identity_op)]
     #[test]
     fn test_field_into_allocated_bits_le() {
@@ -2002,6 +2494,81 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_boolean_conditionally_swap() {
+        let variants = [
+            OperandType::True,
+            OperandType::False,
+            OperandType::AllocatedTrue,
+            OperandType::AllocatedFalse,
+            OperandType::NegatedAllocatedTrue,
+            OperandType::NegatedAllocatedFalse,
+        ];
+
+        for first_operand in variants.iter().cloned() {
+            for second_operand in variants.iter().cloned() {
+                for cond_operand in variants.iter().cloned() {
+                    let mut cs = TestConstraintSystem::<Fr>::new();
+
+                    let a;
+                    let b;
+                    let cond;
+
+                    {
+                        let mut dyn_construct = |operand, name| {
+                            let cs = cs.namespace(|| name);
+
+                            match operand {
+                                OperandType::True => Boolean::constant(true),
+                                OperandType::False => Boolean::constant(false),
+                                OperandType::AllocatedTrue => {
+                                    Boolean::from(AllocatedBit::alloc(cs, Some(true)).unwrap())
+                                }
+                                OperandType::AllocatedFalse => {
+                                    Boolean::from(AllocatedBit::alloc(cs, Some(false)).unwrap())
+                                }
+                                OperandType::NegatedAllocatedTrue => {
+                                    Boolean::from(AllocatedBit::alloc(cs, Some(true)).unwrap())
+                                        .not()
+                                }
+                                OperandType::NegatedAllocatedFalse => {
+                                    Boolean::from(AllocatedBit::alloc(cs, Some(false)).unwrap())
+                                        .not()
+                                }
+                            }
+                        };
+
+                        a = dyn_construct(first_operand, "a");
+                        b = dyn_construct(second_operand, "b");
+                        cond = dyn_construct(cond_operand, "cond");
+                    }
+
+                    let num_constraints = cs.num_constraints();
+                    let (first, second) =
+                        Boolean::conditionally_swap(&mut cs, &a, &b, &cond).unwrap();
+
+                    assert!(cs.is_satisfied());
+
+                    let (expected_first, expected_second) = if cond_operand.val() {
+                        (second_operand.val(), first_operand.val())
+                    } else {
+                        (first_operand.val(), second_operand.val())
+                    };
+                    assert_eq!(first.get_value().unwrap(), expected_first);
+                    assert_eq!(second.get_value().unwrap(), expected_second);
+
+                    if cond_operand.is_constant() {
+                        assert_eq!(cs.num_constraints(), num_constraints);
+                    } else if !first_operand.is_constant() && !second_operand.is_constant() {
+                        let flipped = if expected_first { Fr::ZERO } else { Fr::ONE };
+                        cs.set("first/ch", flipped);
+                        assert_eq!(cs.which_is_unsatisfied().unwrap(), "first/ch computation");
+                    }
+                }
+            }
+        }
+    }
+
     #[test]
     fn test_alloc_conditionally() {
         {
@@ -1,12 +1,19 @@
 //! Gadgets representing numbers in the scalar field of the underlying curve.
 
-use ff::{PrimeField, PrimeFieldBits};
+use std::hash::{Hash, Hasher};
+use std::iter::Sum;
+use std::ops::Range;
+
+use ff::{BatchInvert, PrimeField, PrimeFieldBits};
 use serde::{Deserialize, Serialize};
 
-use crate::{ConstraintSystem, LinearCombination, SynthesisError, Variable};
+use crate::{ConstraintSystem, Index, LinearCombination, Namespace, SynthesisError, Variable};
 
 use crate::gadgets::boolean::{self, AllocatedBit, Boolean};
 
+/// An allocated number. Equality and hashing are by `Variable`, not by
+/// value: two `AllocatedNum`s are equal iff they refer to the same variable
+/// of the constraint system, whatever their assignments.
 #[derive(Debug, Copy, Serialize, Deserialize)]
 pub struct AllocatedNum<Scalar: PrimeField> {
     value: Option<Scalar>,
@@ -15,10 +22,127 @@ pub struct AllocatedNum<Scalar: PrimeField> {
 
 impl<Scalar: PrimeField> Clone for AllocatedNum<Scalar> {
     fn clone(&self) -> Self {
-        AllocatedNum {
-            value: self.value,
-            variable: self.variable,
+        *self
+    }
+}
+
+impl<Scalar: PrimeField> PartialEq for AllocatedNum<Scalar> {
+    fn eq(&self, other: &Self) -> bool {
+        self.variable == other.variable
+    }
+}
+
+impl<Scalar: PrimeField> Eq for AllocatedNum<Scalar> {}
+
+impl<Scalar: PrimeField> Hash for AllocatedNum<Scalar> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.variable.hash(state);
+    }
+}
+
+/// The witness value of an `AllocatedNum`, without its `Variable`. Cached
+/// witnesses serialized this way remain valid when the circuit is
+/// re-synthesized and variables are assigned different indices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct WitnessOnly<Scalar: PrimeField>(pub Option<Scalar>);
+
+impl<Scalar: PrimeField> WitnessOnly<Scalar> {
+    pub fn get_value(&self) -> Option<Scalar> {
+        self.0
+    }
+
+    /// Allocate a fresh `AllocatedNum` holding this witness value.
+    pub fn alloc<CS>(&self, cs: CS) -> Result<AllocatedNum<Scalar>, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        AllocatedNum::alloc(cs, || self.0.ok_or(SynthesisError::AssignmentMissing))
+    }
+}
+
+impl<Scalar: PrimeField> From<&AllocatedNum<Scalar>> for WitnessOnly<Scalar> {
+    fn from(num: &AllocatedNum<Scalar>) -> Self {
+        WitnessOnly(num.value)
+    }
+}
+
+/// An `AllocatedNum` serialized for JSON-based tooling: the value as a
+/// `0x`-prefixed, fixed-width, big-endian hex string and the variable as its
+/// `Index`. Deserializing rejects non-canonical values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllocatedNumHex<Scalar: PrimeField>(pub AllocatedNum<Scalar>);
+
+#[derive(Serialize, Deserialize)]
+struct AllocatedNumHexRepr {
+    value: Option<String>,
+    variable: Index,
+}
+
+fn scalar_to_hex<Scalar: PrimeFieldBits>(value: &Scalar) -> String {
+    use std::fmt::Write;
+
+    let bits = value.to_le_bits();
+    let bits = bits
+        .iter()
+        .take(Scalar::NUM_BITS as usize)
+        .collect::<Vec<_>>();
+
+    let mut hex = String::from("0x");
+    for byte in bits.chunks(8).rev() {
+        let byte = byte
+            .iter()
+            .rev()
+            .fold(0u8, |acc, bit| (acc << 1) | u8::from(**bit));
+        write!(hex, "{:02x}", byte).unwrap();
+    }
+
+    hex
+}
+
+fn scalar_from_hex<Scalar: PrimeFieldBits>(hex: &str) -> Option<Scalar> {
+    let digits = hex.strip_prefix("0x")?;
+    if !digits.is_ascii() || digits.len() % 2 != 0 {
+        return None;
+    }
+
+    let mut value = Scalar::ZERO;
+    for i in (0..digits.len()).step_by(2) {
+        let byte = u8::from_str_radix(&digits[i..i + 2], 16).ok()?;
+        value = value * Scalar::from(256) + Scalar::from(u64::from(byte));
+    }
+
+    // Reject values that wrapped around the modulus or were not written
+    // in canonical form.
+    (scalar_to_hex(&value) == hex.to_ascii_lowercase()).then_some(value)
+}
+
+impl<Scalar: PrimeFieldBits> Serialize for AllocatedNumHex<Scalar> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        AllocatedNumHexRepr {
+            value: self.0.value.as_ref().map(scalar_to_hex),
+            variable: self.0.variable.get_unchecked(),
         }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, Scalar: PrimeFieldBits> Deserialize<'de> for AllocatedNumHex<Scalar> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = AllocatedNumHexRepr::deserialize(deserializer)?;
+        let value = repr
+            .value
+            .map(|hex| {
+                scalar_from_hex(&hex).ok_or_else(|| {
+                    serde::de::Error::custom(format!("invalid field element: {}", hex))
+                })
+            })
+            .transpose()?;
+
+        Ok(AllocatedNumHex(AllocatedNum {
+            value,
+            variable: Variable::new_unchecked(repr.variable),
+        }))
     }
 }
 
@@ -47,6 +171,21 @@ impl<Scalar: PrimeField> AllocatedNum<Scalar> {
         })
     }
 
+    /// Allocate a `Variable(Aux)` in a `ConstraintSystem` from a value that
+    /// is already known. A `None` value only fails with
+    /// `SynthesisError::AssignmentMissing` if `cs` asks for the assignment.
+    pub fn alloc_value<CS>(mut cs: CS, value: Option<Scalar>) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        let var = cs.alloc(|| "num", || value.ok_or(SynthesisError::AssignmentMissing))?;
+
+        Ok(AllocatedNum {
+            value,
+            variable: var,
+        })
+    }
+
     /// Allocate a `Variable(Aux)` in a `ConstraintSystem`. Requires an
     /// infallible getter for the value.
     pub fn alloc_infallible<CS, F>(cs: CS, value: F) -> Self
@@ -101,6 +240,101 @@ impl<Scalar: PrimeField> AllocatedNum<Scalar> {
         }
     }
 
+    /// Allocate a `Variable(Aux)` in a `ConstraintSystem` which is
+    /// constrained to equal the constant `value`.
+    pub fn alloc_constant<CS>(mut cs: CS, value: Scalar) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        let num = Self::alloc(cs.namespace(|| "alloc constant"), || Ok(value))?;
+
+        // Constrain: num * 1 = value
+        cs.enforce(
+            || "constant constraint",
+            |lc| lc + num.variable,
+            |lc| lc + CS::one(),
+            |lc| lc + (value, CS::one()),
+        );
+
+        Ok(num)
+    }
+
+    /// Allocates a public input constrained to equal `CS::one()`, for
+    /// verifiers which expect the constant one as an explicit input.
+    pub fn alloc_input_one<CS>(mut cs: CS) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        let num = Self::alloc_input(cs.namespace(|| "one"), || Ok(Scalar::ONE))?;
+
+        // Constrain: num * 1 = 1
+        cs.enforce(
+            || "one constraint",
+            |lc| lc + num.variable,
+            |lc| lc + CS::one(),
+            |lc| lc + CS::one(),
+        );
+
+        Ok(num)
+    }
+
+    /// Allocates the field element encoded by the little-endian `bytes`,
+    /// returning it with its enforced little-endian bit decomposition, eight
+    /// bits per byte. Returns `IncompatibleLengthVector` if the bytes could
+    /// encode a value outside the field's capacity.
+    pub fn alloc_from_le_bytes<CS>(
+        mut cs: CS,
+        bytes: &[u8],
+    ) -> Result<(Self, Vec<Boolean>), SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+        Scalar: PrimeFieldBits,
+    {
+        let n = bytes.len() * 8;
+        if n > Scalar::CAPACITY as usize {
+            return Err(SynthesisError::IncompatibleLengthVector(format!(
+                "{} bytes exceed the field capacity of {} bits",
+                bytes.len(),
+                Scalar::CAPACITY
+            )));
+        }
+
+        let value = bytes.iter().rev().fold(Scalar::ZERO, |acc, byte| {
+            acc * Scalar::from(256) + Scalar::from(u64::from(*byte))
+        });
+
+        let num = Self::alloc(cs.namespace(|| "num"), || Ok(value))?;
+        let bits = num.to_n_bits_le(cs.namespace(|| "bits"), n)?;
+
+        Ok((num, bits))
+    }
+
+    /// Allocates a number constrained to equal `lc`, carrying `value` as its
+    /// assignment. The caller is responsible for `value` matching `lc`.
+    pub fn from_lc<CS>(
+        mut cs: CS,
+        lc: LinearCombination<Scalar>,
+        value: Option<Scalar>,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        let var = cs.alloc(|| "num", || value.ok_or(SynthesisError::AssignmentMissing))?;
+
+        // Constrain: lc * 1 = num
+        cs.enforce(
+            || "linear combination constraint",
+            |_| lc,
+            |lc| lc + CS::one(),
+            |lc| lc + var,
+        );
+
+        Ok(AllocatedNum {
+            value,
+            variable: var,
+        })
+    }
+
     pub fn inputize<CS>(&self, mut cs: CS) -> Result<(), SynthesisError>
     where
         CS: ConstraintSystem<Scalar>,
@@ -120,6 +354,57 @@ impl<Scalar: PrimeField> AllocatedNum<Scalar> {
         Ok(())
     }
 
+    /// Inputizes each number in `nums`, in namespace `input {i}`.
+    pub fn inputize_vec<CS>(mut cs: CS, nums: &[Self]) -> Result<(), SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        for (i, num) in nums.iter().enumerate() {
+            num.inputize(cs.namespace(|| format!("input {}", i)))?;
+        }
+
+        Ok(())
+    }
+
+    /// Packs the little-endian `bits` into a single public input, enforcing
+    /// that the input equals their weighted sum.
+    pub fn pack_bits_as_input<CS>(mut cs: CS, bits: &[Boolean]) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        assert!(bits.len() <= Scalar::CAPACITY as usize);
+
+        let mut lc = LinearCombination::zero();
+        let mut value = Some(Scalar::ZERO);
+        let mut coeff = Scalar::ONE;
+        for bit in bits {
+            lc = lc + &bit.lc(CS::one(), coeff);
+            value = value.zip(bit.get_value()).map(
+                |(value, bit)| {
+                    if bit {
+                        value + coeff
+                    } else {
+                        value
+                    }
+                },
+            );
+            coeff = coeff.double();
+        }
+
+        let input = Self::alloc_input(cs.namespace(|| "input"), || {
+            value.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        cs.enforce(
+            || "packing constraint",
+            |_| lc,
+            |lc| lc + CS::one(),
+            |lc| lc + input.variable,
+        );
+
+        Ok(input)
+    }
+
     /// Deconstructs this allocated number into its
     /// boolean representation in little-endian bit
     /// order, requiring that the representation
@@ -276,6 +561,117 @@ impl<Scalar: PrimeField> AllocatedNum<Scalar> {
         Ok(bits.into_iter().map(Boolean::from).collect())
     }
 
+    /// Calls `to_bits_le` on each number in `nums`, in namespace `num {i}`.
+    /// This costs `Scalar::NUM_BITS + 1` constraints per number: one
+    /// booleanity constraint per bit and one unpacking constraint.
+    pub fn to_bits_le_many<CS>(
+        mut cs: CS,
+        nums: &[Self],
+    ) -> Result<Vec<Vec<Boolean>>, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+        Scalar: PrimeFieldBits,
+    {
+        nums.iter()
+            .enumerate()
+            .map(|(i, num)| num.to_bits_le(cs.namespace(|| format!("num {}", i))))
+            .collect()
+    }
+
+    /// Decomposes this allocated number into its `n` little-endian bits,
+    /// enforcing that it fits in `n` bits. `n` must not exceed the field's
+    /// capacity, so the decomposition is unique.
+    pub fn to_n_bits_le<CS>(&self, cs: CS, n: usize) -> Result<Vec<Boolean>, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+        Scalar: PrimeFieldBits,
+    {
+        let bits = alloc_packed_bits_le(
+            cs,
+            self.value,
+            LinearCombination::from_variable(self.variable),
+            n,
+        )?;
+
+        Ok(bits.into_iter().map(Boolean::from).collect())
+    }
+
+    /// Returns the bit "this `n`-bit number is even", i.e. the negation of
+    /// its least significant bit. Also enforces that it fits in `n` bits.
+    pub fn is_even<CS>(&self, cs: CS, n: usize) -> Result<Boolean, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+        Scalar: PrimeFieldBits,
+    {
+        assert!(n > 0);
+
+        let bits = self.to_n_bits_le(cs, n)?;
+        Ok(bits[0].not())
+    }
+
+    /// Returns the sign of this `n`-bit number read as two's complement,
+    /// i.e. its top bit: values in `[0, 2^(n-1))` are non-negative and
+    /// values in `[2^(n-1), 2^n)` negative. Also enforces that it fits in
+    /// `n` bits.
+    pub fn sign_bit<CS>(&self, cs: CS, n: usize) -> Result<Boolean, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+        Scalar: PrimeFieldBits,
+    {
+        assert!(n > 0);
+
+        let bits = self.to_n_bits_le(cs, n)?;
+        Ok(bits[n - 1])
+    }
+
+    /// Enforces that this `n`-bit number is even.
+    pub fn enforce_even<CS>(&self, mut cs: CS, n: usize) -> Result<(), SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+        Scalar: PrimeFieldBits,
+    {
+        let is_even = self.is_even(cs.namespace(|| "is even"), n)?;
+        Boolean::enforce_equal(cs.namespace(|| "even"), &is_even, &Boolean::constant(true))
+    }
+
+    /// Enforces that this `n`-bit number is odd.
+    pub fn enforce_odd<CS>(&self, mut cs: CS, n: usize) -> Result<(), SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+        Scalar: PrimeFieldBits,
+    {
+        let is_even = self.is_even(cs.namespace(|| "is even"), n)?;
+        Boolean::enforce_equal(cs.namespace(|| "odd"), &is_even, &Boolean::constant(false))
+    }
+
+    /// Decomposes this allocated number into little-endian windows of
+    /// `window_bits` bits each, as consumed by windowed lookup gadgets.
+    /// The final window is padded with constant `false` bits. Like
+    /// `to_bits_le`, this does not enforce that the decomposition is
+    /// "in the field."
+    pub fn to_windows<CS>(
+        &self,
+        cs: CS,
+        window_bits: usize,
+    ) -> Result<Vec<Vec<Boolean>>, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+        Scalar: PrimeFieldBits,
+    {
+        assert!(window_bits > 0);
+
+        let bits = self.to_bits_le(cs)?;
+
+        Ok(bits
+            .chunks(window_bits)
+            .map(|window| {
+                let mut window = window.to_vec();
+                window.resize(window_bits, Boolean::constant(false));
+                window
+            })
+            .collect())
+    }
+
     pub fn add<CS>(&self, mut cs: CS, other: &Self) -> Result<Self, SynthesisError>
     where
         CS: ConstraintSystem<Scalar>,
@@ -308,18 +704,29 @@ impl<Scalar: PrimeField> AllocatedNum<Scalar> {
         })
     }
 
-    /// Returns (self - other)
-    pub fn sub<CS>(&self, mut cs: CS, other: &Self) -> Result<Self, SynthesisError>
+    /// Returns `self + 1` if `flag` is true, and `self` otherwise. A
+    /// constant false flag returns `self` without any constraints.
+    pub fn conditionally_increment<CS>(
+        &self,
+        mut cs: CS,
+        flag: &Boolean,
+    ) -> Result<Self, SynthesisError>
     where
         CS: ConstraintSystem<Scalar>,
     {
+        if let Boolean::Constant(false) = flag {
+            return Ok(*self);
+        }
+
         let mut value = None;
 
         let var = cs.alloc(
-            || "sub num",
+            || "incremented num",
             || {
                 let mut tmp = self.value.ok_or(SynthesisError::AssignmentMissing)?;
-                tmp.sub_assign(other.value.ok_or(SynthesisError::AssignmentMissing)?);
+                if flag.get_value().ok_or(SynthesisError::AssignmentMissing)? {
+                    tmp.add_assign(Scalar::ONE);
+                }
 
                 value = Some(tmp);
 
@@ -327,10 +734,10 @@ impl<Scalar: PrimeField> AllocatedNum<Scalar> {
             },
         )?;
 
-        // Constrain: (a - b) * 1 = a - b
+        // Constrain: (a + flag) * 1 = out
         cs.enforce(
-            || "subtraction constraint",
-            |lc| lc + self.variable - other.variable,
+            || "increment constraint",
+            |_| flag.lc(CS::one(), Scalar::ONE) + self.variable,
             |lc| lc + CS::one(),
             |lc| lc + var,
         );
@@ -341,17 +748,18 @@ impl<Scalar: PrimeField> AllocatedNum<Scalar> {
         })
     }
 
-    /// Returns (-self)
-    pub fn neg<CS>(&self, mut cs: CS) -> Result<Self, SynthesisError>
+    /// Returns (self - other)
+    pub fn sub<CS>(&self, mut cs: CS, other: &Self) -> Result<Self, SynthesisError>
     where
         CS: ConstraintSystem<Scalar>,
     {
         let mut value = None;
 
         let var = cs.alloc(
-            || "neg num",
+            || "sub num",
             || {
-                let tmp = self.value.ok_or(SynthesisError::AssignmentMissing)?.neg();
+                let mut tmp = self.value.ok_or(SynthesisError::AssignmentMissing)?;
+                tmp.sub_assign(other.value.ok_or(SynthesisError::AssignmentMissing)?);
 
                 value = Some(tmp);
 
@@ -359,12 +767,12 @@ impl<Scalar: PrimeField> AllocatedNum<Scalar> {
             },
         )?;
 
-        // Constrain: (self + var) = 0
+        // Constrain: (a - b) * 1 = a - b
         cs.enforce(
-            || "negation constraint",
-            |lc| lc,
-            |lc| lc,
-            |lc| lc + self.variable + var,
+            || "subtraction constraint",
+            |lc| lc + self.variable - other.variable,
+            |lc| lc + CS::one(),
+            |lc| lc + var,
         );
 
         Ok(AllocatedNum {
@@ -373,56 +781,71 @@ impl<Scalar: PrimeField> AllocatedNum<Scalar> {
         })
     }
 
-    pub fn mul<CS>(&self, mut cs: CS, other: &Self) -> Result<Self, SynthesisError>
+    /// Returns `self - other`, enforcing that the difference fits in `n`
+    /// bits. For `n`-bit operands this proves `self >= other`, so an
+    /// underflow leaves the constraint system unsatisfied.
+    pub fn sub_checked<CS>(
+        &self,
+        mut cs: CS,
+        other: &Self,
+        n: usize,
+    ) -> Result<Self, SynthesisError>
     where
         CS: ConstraintSystem<Scalar>,
+        Scalar: PrimeFieldBits,
     {
-        let mut value = None;
-
-        let var = cs.alloc(
-            || "product num",
-            || {
-                let mut tmp = self.value.ok_or(SynthesisError::AssignmentMissing)?;
-                tmp.mul_assign(other.value.ok_or(SynthesisError::AssignmentMissing)?);
+        let diff = self.sub(cs.namespace(|| "difference"), other)?;
+        diff.to_n_bits_le(cs.namespace(|| "range check"), n)?;
 
-                value = Some(tmp);
+        Ok(diff)
+    }
 
-                Ok(tmp)
-            },
-        )?;
+    /// Returns `self - other`. This is the same as `sub`, for call sites
+    /// where the result is used as a difference, e.g. for diagnostics.
+    pub fn difference<CS>(&self, cs: CS, other: &Self) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        self.sub(cs, other)
+    }
 
-        // Constrain: a * b = ab
+    /// Enforces that this number is 0 or 1 and returns it as a `Boolean`,
+    /// without decomposing it into bits.
+    pub fn enforce_boolean<CS>(&self, mut cs: CS) -> Result<Boolean, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        // Constrain: (1 - a) * a = 0
         cs.enforce(
-            || "multiplication constraint",
+            || "boolean constraint",
+            |lc| lc + CS::one() - self.variable,
             |lc| lc + self.variable,
-            |lc| lc + other.variable,
-            |lc| lc + var,
+            |lc| lc,
         );
 
-        Ok(AllocatedNum {
+        let value = self.value.map(|v| !bool::from(v.is_zero()));
+        Ok(Boolean::from(AllocatedBit::new_unchecked(
+            self.variable,
             value,
-            variable: var,
-        })
+        )))
     }
 
-    /// Returns (self)*(other)^-1
-    pub fn div<CS>(&self, mut cs: CS, other: &Self) -> Result<Self, SynthesisError>
+    /// Returns `c * self` as a `Num`, without adding any constraints.
+    pub fn scalar_mul(&self, c: Scalar) -> Num<Scalar> {
+        Num::from(*self).scale(c)
+    }
+
+    /// Returns `a * self + b` for constants `a` and `b`.
+    pub fn affine<CS>(&self, mut cs: CS, a: Scalar, b: Scalar) -> Result<Self, SynthesisError>
     where
         CS: ConstraintSystem<Scalar>,
     {
         let mut value = None;
 
         let var = cs.alloc(
-            || "div num",
+            || "affine num",
             || {
-                let mut tmp = self.value.ok_or(SynthesisError::AssignmentMissing)?;
-                let other_inv = other
-                    .value
-                    .ok_or(SynthesisError::AssignmentMissing)?
-                    .invert();
-                assert!(other_inv.is_some().unwrap_u8() == 1);
-                let other_inv = other_inv.unwrap();
-                tmp.mul_assign(other_inv);
+                let tmp = self.value.ok_or(SynthesisError::AssignmentMissing)? * a + b;
 
                 value = Some(tmp);
 
@@ -430,10 +853,313 @@ impl<Scalar: PrimeField> AllocatedNum<Scalar> {
             },
         )?;
 
-        // Constrain: var * other = self
+        // Constrain: self * a = out - b
         cs.enforce(
-            || "division constraint",
-            |lc| lc + var,
+            || "affine constraint",
+            |lc| lc + self.variable,
+            |lc| lc + (a, CS::one()),
+            |lc| lc + var - (b, CS::one()),
+        );
+
+        Ok(AllocatedNum {
+            value,
+            variable: var,
+        })
+    }
+
+    /// Returns the running sums of `values`, where the `i`th sum includes
+    /// `values[j]` for each `j <= i` whose flag is set. The sums themselves
+    /// are linear combinations and cost nothing; only the masked value
+    /// `values[i] * flags[i]` of an allocated flag needs a constraint, as it
+    /// is a product of two variables. Constant flags are folded away.
+    pub fn conditional_scan<CS>(
+        mut cs: CS,
+        values: &[Self],
+        flags: &[Boolean],
+    ) -> Result<Vec<Num<Scalar>>, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        if values.len() != flags.len() {
+            return Err(SynthesisError::IncompatibleLengthVector(format!(
+                "{} values but {} flags",
+                values.len(),
+                flags.len()
+            )));
+        }
+
+        let mut acc = Num::zero();
+        let mut sums = Vec::with_capacity(values.len());
+
+        for (i, (value, flag)) in values.iter().zip(flags).enumerate() {
+            match flag {
+                Boolean::Constant(false) => {}
+                Boolean::Constant(true) => acc = acc.add(&Num::from(*value)),
+                _ => {
+                    let mut cs = cs.namespace(|| format!("value {}", i));
+
+                    let masked = Self::alloc(cs.namespace(|| "masked value"), || {
+                        let value = value.value.ok_or(SynthesisError::AssignmentMissing)?;
+                        let flag = flag.get_value().ok_or(SynthesisError::AssignmentMissing)?;
+                        Ok(if flag { value } else { Scalar::ZERO })
+                    })?;
+
+                    // Constrain: value * flag = masked
+                    cs.enforce(
+                        || "masking constraint",
+                        |lc| lc + value.variable,
+                        |_| flag.lc(CS::one(), Scalar::ONE),
+                        |lc| lc + masked.variable,
+                    );
+
+                    acc = acc.add(&Num::from(masked));
+                }
+            }
+
+            sums.push(acc.clone());
+        }
+
+        Ok(sums)
+    }
+
+    /// Returns `(self >> shift, self mod 2^shift)` for an `n`-bit `self`,
+    /// enforcing `self = quotient * 2^shift + remainder` with the quotient
+    /// range-checked to `n - shift` bits and the remainder to `shift` bits.
+    pub fn shr_constant<CS>(
+        &self,
+        mut cs: CS,
+        shift: usize,
+        n: usize,
+    ) -> Result<(Self, Self), SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+        Scalar: PrimeFieldBits,
+    {
+        assert!(shift <= n);
+        assert!(n <= Scalar::CAPACITY as usize);
+
+        let (q_value, r_value) = match self.value {
+            Some(value) => {
+                let bits = value.to_le_bits();
+                let pack = |bits: &[bool]| {
+                    bits.iter().rev().fold(Scalar::ZERO, |acc, bit| {
+                        if *bit {
+                            acc.double() + Scalar::ONE
+                        } else {
+                            acc.double()
+                        }
+                    })
+                };
+                let bits = bits.iter().take(n).map(|b| *b).collect::<Vec<_>>();
+                (Some(pack(&bits[shift..])), Some(pack(&bits[..shift])))
+            }
+            None => (None, None),
+        };
+
+        let q = Self::alloc(cs.namespace(|| "quotient"), || {
+            q_value.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let r = Self::alloc(cs.namespace(|| "remainder"), || {
+            r_value.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        // Constrain: (q * 2^shift + r) * 1 = self
+        cs.enforce(
+            || "shift constraint",
+            |lc| lc + (Scalar::from(2).pow_vartime([shift as u64]), q.variable) + r.variable,
+            |lc| lc + CS::one(),
+            |lc| lc + self.variable,
+        );
+
+        q.to_n_bits_le(cs.namespace(|| "quotient range check"), n - shift)?;
+        r.to_n_bits_le(cs.namespace(|| "remainder range check"), shift)?;
+
+        Ok((q, r))
+    }
+
+    /// Returns `self mod modulus`, where `self` is assumed to fit in `n`
+    /// bits. This witnesses `q` and `r` with `self = q * modulus + r`,
+    /// range-checking `q` to `n` bits and `r` to `[0, modulus)`.
+    pub fn reduce_mod_constant<CS>(
+        &self,
+        mut cs: CS,
+        modulus: u64,
+        n: usize,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+        Scalar: PrimeFieldBits,
+    {
+        assert!(modulus > 0);
+
+        // Number of bits needed for a remainder of at most `modulus - 1`.
+        let k = (u64::BITS - (modulus - 1).leading_zeros()) as usize;
+        // Ensures `q * modulus + r < 2^(n + k)` cannot wrap around the field.
+        assert!(n + k < Scalar::CAPACITY as usize);
+
+        // Long division of the bits of `self`, most significant first.
+        let (q_value, r_value) = match self.value {
+            Some(value) => {
+                let mut q = Scalar::ZERO;
+                let mut r = 0u128;
+                for bit in value.to_le_bits().iter().rev() {
+                    r = (r << 1) | u128::from(*bit);
+                    q = q.double();
+                    if r >= u128::from(modulus) {
+                        r -= u128::from(modulus);
+                        q += Scalar::ONE;
+                    }
+                }
+                (Some(q), Some(Scalar::from(r as u64)))
+            }
+            None => (None, None),
+        };
+
+        let q = Self::alloc(cs.namespace(|| "quotient"), || {
+            q_value.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let r = Self::alloc(cs.namespace(|| "remainder"), || {
+            r_value.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        // Constrain: (q * modulus + r) * 1 = self
+        cs.enforce(
+            || "division constraint",
+            |lc| lc + (Scalar::from(modulus), q.variable) + r.variable,
+            |lc| lc + CS::one(),
+            |lc| lc + self.variable,
+        );
+
+        q.to_n_bits_le(cs.namespace(|| "quotient range check"), n)?;
+        r.to_n_bits_le(cs.namespace(|| "remainder range check"), k)?;
+
+        // With `0 <= r < 2^k`, `r < modulus` iff `r + 2^k - modulus` also
+        // fits in `k` bits.
+        let offset = Scalar::from(2).pow_vartime([k as u64]) - Scalar::from(modulus);
+        alloc_packed_bits_le(
+            cs.namespace(|| "remainder bound"),
+            r_value.map(|r| r + offset),
+            LinearCombination::zero() + r.variable + (offset, CS::one()),
+            k,
+        )?;
+
+        Ok(r)
+    }
+
+    /// Returns (-self)
+    pub fn neg<CS>(&self, mut cs: CS) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        let mut value = None;
+
+        let var = cs.alloc(
+            || "neg num",
+            || {
+                let tmp = self.value.ok_or(SynthesisError::AssignmentMissing)?.neg();
+
+                value = Some(tmp);
+
+                Ok(tmp)
+            },
+        )?;
+
+        // Constrain: (self + var) = 0
+        cs.enforce(
+            || "negation constraint",
+            |lc| lc,
+            |lc| lc,
+            |lc| lc + self.variable + var,
+        );
+
+        Ok(AllocatedNum {
+            value,
+            variable: var,
+        })
+    }
+
+    pub fn mul<CS>(&self, mut cs: CS, other: &Self) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        let mut value = None;
+
+        let var = cs.alloc(
+            || "product num",
+            || {
+                let mut tmp = self.value.ok_or(SynthesisError::AssignmentMissing)?;
+                tmp.mul_assign(other.value.ok_or(SynthesisError::AssignmentMissing)?);
+
+                value = Some(tmp);
+
+                Ok(tmp)
+            },
+        )?;
+
+        // Constrain: a * b = ab
+        cs.enforce(
+            || "multiplication constraint",
+            |lc| lc + self.variable,
+            |lc| lc + other.variable,
+            |lc| lc + var,
+        );
+
+        Ok(AllocatedNum {
+            value,
+            variable: var,
+        })
+    }
+
+    /// Enforces `a * b = c` over existing variables, in a single constraint
+    /// and without allocating a product.
+    pub fn enforce_product<CS>(
+        mut cs: CS,
+        a: &Self,
+        b: &Self,
+        c: &Self,
+    ) -> Result<(), SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        cs.enforce(
+            || "product constraint",
+            |lc| lc + a.variable,
+            |lc| lc + b.variable,
+            |lc| lc + c.variable,
+        );
+
+        Ok(())
+    }
+
+    /// Returns (self)*(other)^-1
+    pub fn div<CS>(&self, mut cs: CS, other: &Self) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        let mut value = None;
+
+        let var = cs.alloc(
+            || "div num",
+            || {
+                let mut tmp = self.value.ok_or(SynthesisError::AssignmentMissing)?;
+                let other_inv = other
+                    .value
+                    .ok_or(SynthesisError::AssignmentMissing)?
+                    .invert();
+                assert!(other_inv.is_some().unwrap_u8() == 1);
+                let other_inv = other_inv.unwrap();
+                tmp.mul_assign(other_inv);
+
+                value = Some(tmp);
+
+                Ok(tmp)
+            },
+        )?;
+
+        // Constrain: var * other = self
+        cs.enforce(
+            || "division constraint",
+            |lc| lc + var,
             |lc| lc + other.variable,
             |lc| lc + self.variable,
         );
@@ -476,10 +1202,44 @@ impl<Scalar: PrimeField> AllocatedNum<Scalar> {
         })
     }
 
-    pub fn assert_nonzero<CS>(&self, mut cs: CS) -> Result<(), SynthesisError>
+    /// Returns `self^5` in three constraints, as `x^4 * x`.
+    pub fn pow5<CS>(&self, mut cs: CS) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        let x2 = self.square(cs.namespace(|| "x^2"))?;
+        let x4 = x2.square(cs.namespace(|| "x^4"))?;
+        x4.mul(cs.namespace(|| "x^5"), self)
+    }
+
+    /// Returns `self^7` in four constraints, as `x^6 * x` with
+    /// `x^6 = (x^2 * x)^2`.
+    pub fn pow7<CS>(&self, mut cs: CS) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        let x2 = self.square(cs.namespace(|| "x^2"))?;
+        let x3 = x2.mul(cs.namespace(|| "x^3"), self)?;
+        let x6 = x3.square(cs.namespace(|| "x^6"))?;
+        x6.mul(cs.namespace(|| "x^7"), self)
+    }
+
+    pub fn assert_nonzero<CS>(&self, cs: CS) -> Result<(), SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        self.enforce_nonzero(cs)?;
+        Ok(())
+    }
+
+    /// Enforces that this number is nonzero and returns its allocated
+    /// inverse, using the single constraint `self * inv = 1`.
+    pub fn enforce_nonzero<CS>(&self, mut cs: CS) -> Result<Self, SynthesisError>
     where
         CS: ConstraintSystem<Scalar>,
     {
+        let mut value = None;
+
         let inv = cs.alloc(
             || "ephemeral inverse",
             || {
@@ -488,7 +1248,9 @@ impl<Scalar: PrimeField> AllocatedNum<Scalar> {
                 if tmp.is_zero().into() {
                     Err(SynthesisError::DivisionByZero)
                 } else {
-                    Ok(tmp.invert().unwrap())
+                    let inv = tmp.invert().unwrap();
+                    value = Some(inv);
+                    Ok(inv)
                 }
             },
         )?;
@@ -503,66 +1265,639 @@ impl<Scalar: PrimeField> AllocatedNum<Scalar> {
             |lc| lc + CS::one(),
         );
 
+        Ok(AllocatedNum {
+            value,
+            variable: inv,
+        })
+    }
+
+    /// Enforces `a * b = 1`, proving that `b` is the inverse of `a`
+    /// (and hence that both are nonzero) without allocating anything.
+    pub fn enforce_product_is_one<CS>(mut cs: CS, a: &Self, b: &Self) -> Result<(), SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        // Constrain: a * b = 1
+        cs.enforce(
+            || "product is one constraint",
+            |lc| lc + a.variable,
+            |lc| lc + b.variable,
+            |lc| lc + CS::one(),
+        );
+
         Ok(())
     }
 
-    /// Returns the bit `self == 0`
-    pub fn is_zero<CS>(&self, mut cs: CS) -> Result<Boolean, SynthesisError>
+    /// Enforces `a * b = expected` for a constant `expected`, folding the
+    /// constant into the constraint rather than allocating it.
+    pub fn enforce_units_product<CS>(
+        mut cs: CS,
+        a: &Self,
+        b: &Self,
+        expected: Scalar,
+    ) -> Result<(), SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        // Constrain: a * b = expected
+        cs.enforce(
+            || "units product constraint",
+            |lc| lc + a.variable,
+            |lc| lc + b.variable,
+            |lc| lc + (expected, CS::one()),
+        );
+
+        Ok(())
+    }
+
+    /// Returns `(root, is_residue)`: a square root of `self` and `true` if
+    /// `self` is a quadratic residue, or zero and `false` otherwise, so the
+    /// gadget stays satisfiable for any input.
+    ///
+    /// `is_residue` cannot be forged: a witness `w` is always enforced to
+    /// satisfy `w^2 = self` when the flag is set, and `w^2 = self * g` for
+    /// the non-residue `g = MULTIPLICATIVE_GENERATOR` when it is clear, which
+    /// has a solution only when `self` is not a residue. The one exception
+    /// is `self == 0`, where either flag is accepted and `root` is zero.
+    pub fn sqrt_or_zero<CS>(&self, mut cs: CS) -> Result<(Self, Boolean), SynthesisError>
     where
         CS: ConstraintSystem<Scalar>,
     {
-        let out = AllocatedBit::alloc(&mut cs.namespace(|| "out bit"), {
-            let input_value = self.value.ok_or(SynthesisError::AssignmentMissing)?;
-            Some(input_value == Scalar::ZERO)
+        let g = Scalar::MULTIPLICATIVE_GENERATOR;
+
+        let witness = self.value.map(|value| {
+            let root = value.sqrt();
+            if bool::from(root.is_some()) {
+                (true, root.unwrap())
+            } else {
+                (false, (value * g).sqrt().unwrap())
+            }
+        });
+
+        let is_residue = AllocatedBit::alloc(
+            cs.namespace(|| "is residue"),
+            witness.map(|(is_residue, _)| is_residue),
+        )?;
+        let w = Self::alloc(cs.namespace(|| "witness root"), || {
+            witness
+                .map(|(_, w)| w)
+                .ok_or(SynthesisError::AssignmentMissing)
         })?;
-        let multiplier = Self::alloc(&mut cs.namespace(|| "zero or inverse"), || {
-            let tmp = self.value.ok_or(SynthesisError::AssignmentMissing)?;
 
-            if tmp.is_zero().into() {
+        // t = self when `self` is not a residue, and zero otherwise.
+        let t = Self::alloc(cs.namespace(|| "non-residue term"), || {
+            let (is_residue, _) = witness.ok_or(SynthesisError::AssignmentMissing)?;
+            if is_residue {
                 Ok(Scalar::ZERO)
             } else {
-                Ok(tmp.invert().unwrap())
+                Ok(self.value.unwrap())
             }
         })?;
 
+        // Constrain: self * (1 - is_residue) = t
         cs.enforce(
-            || "multiplier * input === 1 - out",
-            |lc| lc + multiplier.variable,
+            || "non-residue term constraint",
             |lc| lc + self.variable,
-            |lc| lc + CS::one() - out.get_variable(),
+            |lc| lc + CS::one() - is_residue.get_variable(),
+            |lc| lc + t.variable,
         );
 
+        // Constrain: w * w = self + (g - 1) * t
         cs.enforce(
-            || "out * input === 0",
-            |lc| lc + out.get_variable(),
-            |lc| lc + self.variable,
-            |lc| lc,
+            || "residue constraint",
+            |lc| lc + w.variable,
+            |lc| lc + w.variable,
+            |lc| lc + self.variable + (g - Scalar::ONE, t.variable),
         );
-        Ok(Boolean::from(out))
+
+        let root = Self::alloc(cs.namespace(|| "root"), || {
+            let (is_residue, w) = witness.ok_or(SynthesisError::AssignmentMissing)?;
+            if is_residue {
+                Ok(w)
+            } else {
+                Ok(Scalar::ZERO)
+            }
+        })?;
+
+        // Constrain: w * is_residue = root
+        cs.enforce(
+            || "root constraint",
+            |lc| lc + w.variable,
+            |lc| lc + is_residue.get_variable(),
+            |lc| lc + root.variable,
+        );
+
+        Ok((root, Boolean::from(is_residue)))
     }
 
-    /// Takes two allocated numbers (self, other) and returns
-    /// the bit `self==other`
-    pub fn is_equal<CS>(&self, mut cs: CS, other: &Self) -> Result<Boolean, SynthesisError>
+    /// Returns the bit `self == 0`
+    pub fn is_zero<CS>(&self, cs: CS) -> Result<Boolean, SynthesisError>
     where
         CS: ConstraintSystem<Scalar>,
     {
-        let diff = self.sub(&mut cs.namespace(|| "self-other"), other)?;
-        Self::is_zero(&diff, cs)
+        Self::is_zero_lc(cs, self.value, LinearCombination::zero() + self.variable)
     }
 
-    /// Takes two allocated numbers (a, b) and returns
-    /// a if condition is false, and b otherwise
-    pub fn conditionally_select<CS>(
+    /// Returns the bit `lc == 0`, where `value` is the value of `lc`. This
+    /// lets callers test a linear combination without first allocating it.
+    fn is_zero_lc<CS>(
+        cs: CS,
+        value: Option<Scalar>,
+        lc: LinearCombination<Scalar>,
+    ) -> Result<Boolean, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        let inverse = value.map(|value| value.invert().unwrap_or(Scalar::ZERO));
+        Self::is_zero_lc_with_inverse(cs, value, inverse, lc)
+    }
+
+    /// Like `is_zero_lc`, with the witness inverse of `value` (zero if
+    /// `value` is zero) supplied by the caller.
+    fn is_zero_lc_with_inverse<CS>(
         mut cs: CS,
-        a: &Self,
-        b: &Self,
-        condition: &Boolean,
-    ) -> Result<Self, SynthesisError>
+        value: Option<Scalar>,
+        inverse: Option<Scalar>,
+        lc: LinearCombination<Scalar>,
+    ) -> Result<Boolean, SynthesisError>
     where
         CS: ConstraintSystem<Scalar>,
     {
-        let c = Self::alloc(&mut cs.namespace(|| "alloc output"), || {
+        let out = AllocatedBit::alloc(
+            &mut cs.namespace(|| "out bit"),
+            value.map(|value| value == Scalar::ZERO),
+        )?;
+        let multiplier = Self::alloc(&mut cs.namespace(|| "zero or inverse"), || {
+            inverse.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        cs.enforce(
+            || "multiplier * input === 1 - out",
+            |lc| lc + multiplier.variable,
+            |_| lc.clone(),
+            |lc| lc + CS::one() - out.get_variable(),
+        );
+
+        cs.enforce(
+            || "out * input === 0",
+            |lc| lc + out.get_variable(),
+            |_| lc,
+            |lc| lc,
+        );
+        Ok(Boolean::from(out))
+    }
+
+    /// Returns the bit `num == 0` for each of `nums`. The constraints are
+    /// the same as calling `is_zero` on every element, but the witness
+    /// inverses are computed with a single batch inversion.
+    pub fn is_zero_many<CS>(mut cs: CS, nums: &[Self]) -> Result<Vec<Boolean>, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        let values = nums.iter().map(|num| num.value).collect::<Option<Vec<_>>>();
+        let inverses = values.map(|mut values| {
+            values.iter_mut().batch_invert();
+            values
+        });
+
+        nums.iter()
+            .enumerate()
+            .map(|(i, num)| {
+                Self::is_zero_lc_with_inverse(
+                    cs.namespace(|| format!("num {i}")),
+                    num.value,
+                    inverses.as_ref().map(|inverses| inverses[i]),
+                    LinearCombination::zero() + num.variable,
+                )
+            })
+            .collect()
+    }
+
+    /// Takes two allocated numbers (self, other) and returns
+    /// the bit `self==other`
+    pub fn is_equal<CS>(&self, cs: CS, other: &Self) -> Result<Boolean, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        // Test `self - other` directly rather than allocating the difference.
+        let value = self.value.zip(other.value).map(|(a, b)| a - b);
+        Self::is_zero_lc(
+            cs,
+            value,
+            LinearCombination::zero() + self.variable - other.variable,
+        )
+    }
+
+    /// Like `is_equal`, but also returns the allocated difference
+    /// `self - other` the equality bit was computed from.
+    pub fn is_equal_with_diff<CS>(
+        &self,
+        mut cs: CS,
+        other: &Self,
+    ) -> Result<(Boolean, Self), SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        let diff = self.difference(&mut cs.namespace(|| "self-other"), other)?;
+        let is_equal = Self::is_zero(&diff, cs)?;
+        Ok((is_equal, diff))
+    }
+
+    /// Enforces `a == b` when `flag` is true, with the constraint
+    /// `flag * (a - b) = 0`. If the witness has `flag` set while `a` and `b`
+    /// differ, this returns `SynthesisError::Unsatisfiable` straight away
+    /// instead of leaving an unsatisfied constraint behind.
+    pub fn enforce_equal_if<CS>(
+        mut cs: CS,
+        flag: &Boolean,
+        a: &Self,
+        b: &Self,
+    ) -> Result<(), SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        if let Boolean::Constant(false) = flag {
+            return Ok(());
+        }
+
+        if let (Some(true), Some(a), Some(b)) = (flag.get_value(), a.value, b.value) {
+            if a != b {
+                return Err(SynthesisError::Unsatisfiable);
+            }
+        }
+
+        cs.enforce(
+            || "conditional equality constraint",
+            |_| flag.lc(CS::one(), Scalar::ONE),
+            |lc| lc + a.variable - b.variable,
+            |lc| lc,
+        );
+
+        Ok(())
+    }
+
+    /// Enforces that every number in `nums` equals the first one, using
+    /// `nums.len() - 1` linear constraints. Empty and single-element
+    /// slices are trivially equal and add no constraints.
+    pub fn enforce_all_equal<CS>(mut cs: CS, nums: &[Self]) -> Result<(), SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        if let Some((first, rest)) = nums.split_first() {
+            for (i, num) in rest.iter().enumerate() {
+                // Constrain: (first - num) * 1 = 0
+                cs.enforce(
+                    || format!("num {} equals num 0", i + 1),
+                    |lc| lc + first.variable - num.variable,
+                    |lc| lc + CS::one(),
+                    |lc| lc,
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the bit "at least one number in `nums` is nonzero", or
+    /// `false` for an empty slice.
+    pub fn is_any_nonzero<CS>(mut cs: CS, nums: &[Self]) -> Result<Boolean, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        let mut acc = Boolean::constant(false);
+        for (i, num) in nums.iter().enumerate() {
+            let is_zero = num.is_zero(cs.namespace(|| format!("num {} is zero", i)))?;
+            acc = Boolean::or(cs.namespace(|| format!("or {}", i)), &acc, &is_zero.not())?;
+        }
+
+        Ok(acc)
+    }
+
+    /// Returns the linear combination `self - s` and its value.
+    fn sub_constant_lc<CS>(&self, s: Scalar) -> (LinearCombination<Scalar>, Option<Scalar>)
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        (
+            LinearCombination::zero() + self.variable - (s, CS::one()),
+            self.value.map(|v| v - s),
+        )
+    }
+
+    /// Computes `prod(self - s_i)` over `set` as a linear combination,
+    /// allocating the `set.len() - 1` intermediate products. The product
+    /// of an empty set is one.
+    fn product_of_differences<CS>(
+        &self,
+        mut cs: CS,
+        set: &[Scalar],
+    ) -> Result<(LinearCombination<Scalar>, Option<Scalar>), SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        let (first, rest) = match set.split_first() {
+            Some(split) => split,
+            None => return Ok((LinearCombination::zero() + CS::one(), Some(Scalar::ONE))),
+        };
+
+        let (mut acc, mut acc_value) = self.sub_constant_lc::<CS>(*first);
+        for (i, s) in rest.iter().enumerate() {
+            let (diff, diff_value) = self.sub_constant_lc::<CS>(*s);
+            let value = acc_value.zip(diff_value).map(|(a, b)| a * b);
+
+            let product = cs.alloc(
+                || format!("product {}", i + 1),
+                || value.ok_or(SynthesisError::AssignmentMissing),
+            )?;
+
+            cs.enforce(
+                || format!("product {} constraint", i + 1),
+                |_| acc,
+                |_| diff,
+                |lc| lc + product,
+            );
+
+            acc = LinearCombination::from_variable(product);
+            acc_value = value;
+        }
+
+        Ok((acc, acc_value))
+    }
+
+    /// Enforces that this number equals one of the constants in `set`, by
+    /// constraining `prod(self - s_i) = 0` with `set.len() - 1`
+    /// multiplications. An empty set is unsatisfiable.
+    pub fn enforce_member_of<CS>(&self, mut cs: CS, set: &[Scalar]) -> Result<(), SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        let (last, rest) = set.split_last().ok_or(SynthesisError::Unsatisfiable)?;
+
+        let (acc, _) = self.product_of_differences(&mut cs, rest)?;
+        let (diff, _) = self.sub_constant_lc::<CS>(*last);
+
+        cs.enforce(|| "membership constraint", |_| acc, |_| diff, |lc| lc);
+
+        Ok(())
+    }
+
+    /// Returns the bit `self` is one of the constants in `set`.
+    pub fn is_member_of<CS>(&self, mut cs: CS, set: &[Scalar]) -> Result<Boolean, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        let (last, rest) = match set.split_last() {
+            Some(split) => split,
+            None => return Ok(Boolean::constant(false)),
+        };
+
+        let (acc, acc_value) = self.product_of_differences(&mut cs, rest)?;
+        let (diff, diff_value) = self.sub_constant_lc::<CS>(*last);
+        let value = acc_value.zip(diff_value).map(|(a, b)| a * b);
+
+        let product = cs.alloc(
+            || "product",
+            || value.ok_or(SynthesisError::AssignmentMissing),
+        )?;
+
+        cs.enforce(
+            || "product constraint",
+            |_| acc,
+            |_| diff,
+            |lc| lc + product,
+        );
+
+        let product = AllocatedNum {
+            value,
+            variable: product,
+        };
+        product.is_zero(cs.namespace(|| "product is zero"))
+    }
+
+    /// Returns the bit `self == k` for the constant index `k`.
+    pub fn index_equals<CS>(&self, cs: CS, k: u64) -> Result<Boolean, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        let (diff, diff_value) = self.sub_constant_lc::<CS>(Scalar::from(k));
+        Self::is_zero_lc(cs, diff_value, diff)
+    }
+
+    /// Decodes `self` into one bit `self == k` per index `k` in `range`.
+    /// The bits are enforced to be one-hot with `sum(k * bit_k) = self`, so
+    /// this is unsatisfiable if `self` lies outside `range`, and an empty
+    /// range returns `SynthesisError::Unsatisfiable`.
+    pub fn index_equals_all<CS>(
+        &self,
+        mut cs: CS,
+        range: Range<u64>,
+    ) -> Result<Vec<Boolean>, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        if range.is_empty() {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        let bits = range
+            .clone()
+            .map(|k| {
+                let bit = AllocatedBit::alloc(
+                    cs.namespace(|| format!("index {k}")),
+                    self.value.map(|v| v == Scalar::from(k)),
+                )?;
+                Ok(Boolean::from(bit))
+            })
+            .collect::<Result<Vec<_>, SynthesisError>>()?;
+
+        Boolean::enforce_exactly_one(cs.namespace(|| "one hot"), &bits)?;
+
+        let weighted_sum = bits
+            .iter()
+            .zip(range)
+            .fold(LinearCombination::zero(), |lc, (bit, k)| {
+                lc + &bit.lc(CS::one(), Scalar::from(k))
+            });
+        cs.enforce(
+            || "weighted sum constraint",
+            |_| weighted_sum,
+            |lc| lc + CS::one(),
+            |lc| lc + self.variable,
+        );
+
+        Ok(bits)
+    }
+
+    /// Returns the bit `a < b`, where `a` and `b` are both assumed to fit
+    /// in `n` bits; callers must range-check them separately. This
+    /// decomposes `a - b + 2^n` into `n + 1` bits, whose top bit is set
+    /// iff `a >= b`.
+    pub fn is_less_than<CS>(
+        mut cs: CS,
+        a: &Self,
+        b: &Self,
+        n: usize,
+    ) -> Result<Boolean, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+        Scalar: PrimeFieldBits,
+    {
+        assert!(n < Scalar::CAPACITY as usize);
+
+        let offset = Scalar::from(2).pow_vartime([n as u64]);
+        let value = match (a.value, b.value) {
+            (Some(a), Some(b)) => Some(a - b + offset),
+            _ => None,
+        };
+
+        let bits = alloc_packed_bits_le(
+            cs.namespace(|| "a - b + 2^n"),
+            value,
+            LinearCombination::zero() + a.variable - b.variable + (offset, CS::one()),
+            n + 1,
+        )?;
+
+        Ok(Boolean::from(bits[n]).not())
+    }
+
+    /// Returns `|a - b|`, where `a` and `b` are both assumed to fit in `n`
+    /// bits as for `is_less_than`. The comparison `a < b` orders the pair
+    /// before subtracting, and the difference is range-checked to `n` bits,
+    /// so a prover cannot swap the branches to get `b - a` wrapped around
+    /// the field.
+    pub fn abs_diff<CS>(mut cs: CS, a: &Self, b: &Self, n: usize) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+        Scalar: PrimeFieldBits,
+    {
+        let lt = Self::is_less_than(cs.namespace(|| "a < b"), a, b, n)?;
+        let (lo, hi) = Self::conditionally_reverse(cs.namespace(|| "order"), a, b, &lt.not())?;
+        let diff = hi.sub(cs.namespace(|| "hi - lo"), &lo)?;
+        diff.to_n_bits_le(cs.namespace(|| "diff range check"), n)?;
+
+        Ok(diff)
+    }
+
+    /// Enforces `self <= bound` for a constant `bound`, as integers. With
+    /// `n` the bit length of `bound`, both `self` and `bound - self` are
+    /// decomposed into `n` bits; if `self > bound`, the field difference
+    /// wraps around and cannot fit. Bounds too wide for this, with `n` not
+    /// below the field's capacity, are compared against the canonical bits
+    /// of `self` instead.
+    pub fn enforce_le_constant<CS>(&self, mut cs: CS, bound: Scalar) -> Result<(), SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+        Scalar: PrimeFieldBits,
+    {
+        let bound_bits = bound.to_le_bits();
+        let n = bound_bits.iter().rposition(|b| *b).map_or(0, |i| i + 1);
+
+        if n >= Scalar::CAPACITY as usize {
+            let bits = self.to_bits_le_strict(cs.namespace(|| "self bits"))?;
+
+            // From the most significant bit down, `eq` is whether `self`
+            // matches `bound` so far. While it does, `self` may not have a
+            // bit set where `bound` has none. Bits below the lowest unset
+            // bit of `bound` can never exceed it.
+            let lowest_unset = match bound_bits.iter().position(|b| !*b) {
+                Some(i) => i,
+                None => return Ok(()),
+            };
+            let mut eq = Boolean::constant(true);
+            for (i, (bit, bound_bit)) in bits.iter().zip(bound_bits).enumerate().rev() {
+                if i < lowest_unset {
+                    break;
+                }
+
+                if bound_bit {
+                    eq = Boolean::and(cs.namespace(|| format!("bit {} equal", i)), &eq, bit)?;
+                } else {
+                    // Constrain: eq * bit = 0
+                    cs.enforce(
+                        || format!("bit {} constraint", i),
+                        |_| eq.lc(CS::one(), Scalar::ONE),
+                        |_| bit.lc(CS::one(), Scalar::ONE),
+                        |lc| lc,
+                    );
+                }
+            }
+
+            return Ok(());
+        }
+
+        self.to_n_bits_le(cs.namespace(|| "self range check"), n)?;
+        alloc_packed_bits_le(
+            cs.namespace(|| "bound - self range check"),
+            self.value.map(|v| bound - v),
+            LinearCombination::zero() + (bound, CS::one()) - self.variable,
+            n,
+        )?;
+
+        Ok(())
+    }
+
+    /// Returns the bit `a < b` for two big integers given as little-endian
+    /// vectors of limbs. Each limb is range-checked to `limb_bits` bits, and
+    /// the limbs are compared lexicographically from the most significant
+    /// one down.
+    pub fn is_less_than_limbs<CS>(
+        mut cs: CS,
+        a: &[Self],
+        b: &[Self],
+        limb_bits: usize,
+    ) -> Result<Boolean, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+        Scalar: PrimeFieldBits,
+    {
+        if a.len() != b.len() {
+            return Err(SynthesisError::IncompatibleLengthVector(format!(
+                "a has {} limbs but b has {}",
+                a.len(),
+                b.len()
+            )));
+        }
+
+        // Folding from the least significant limb up, `acc` is `a < b`
+        // restricted to the limbs seen so far: a more significant limb
+        // decides the comparison unless its two sides are equal.
+        let mut acc = Boolean::constant(false);
+        for (i, (a, b)) in a.iter().zip(b).enumerate() {
+            let mut cs = cs.namespace(|| format!("limb {}", i));
+
+            a.to_n_bits_le(cs.namespace(|| "a range check"), limb_bits)?;
+            b.to_n_bits_le(cs.namespace(|| "b range check"), limb_bits)?;
+
+            let lt = Self::is_less_than(cs.namespace(|| "a < b"), a, b, limb_bits)?;
+            let eq = a.is_equal(cs.namespace(|| "a == b"), b)?;
+
+            let eq_and_acc =
+                Boolean::and(cs.namespace(|| "a == b and lower limbs less"), &eq, &acc)?;
+            acc = Boolean::or(cs.namespace(|| "result"), &lt, &eq_and_acc)?;
+        }
+
+        Ok(acc)
+    }
+
+    /// Takes two allocated numbers (a, b) and returns
+    /// a if condition is false, and b otherwise
+    pub fn conditionally_select<CS>(
+        mut cs: CS,
+        a: &Self,
+        b: &Self,
+        condition: &Boolean,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        // A constant condition selects a branch without any constraints.
+        match *condition {
+            Boolean::Constant(false) => return Ok(*a),
+            Boolean::Constant(true) => return Ok(*b),
+            _ => {}
+        }
+
+        let c = Self::alloc(&mut cs.namespace(|| "alloc output"), || {
             if condition
                 .get_value()
                 .ok_or(SynthesisError::AssignmentMissing)?
@@ -582,6 +1917,68 @@ impl<Scalar: PrimeField> AllocatedNum<Scalar> {
         Ok(c)
     }
 
+    /// Enforces that `out` equals a if condition is false, and b otherwise.
+    /// Unlike `conditionally_select`, nothing is allocated.
+    pub fn enforce_select<CS>(
+        mut cs: CS,
+        out: &Self,
+        a: &Self,
+        b: &Self,
+        condition: &Boolean,
+    ) -> Result<(), SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        // Constrain: condition * (b - a) = out - a
+        cs.enforce(
+            || "condition * (b - a) === out - a",
+            |_| condition.lc(CS::one(), Scalar::ONE),
+            |lc| lc + b.variable - a.variable,
+            |lc| lc + out.variable - a.variable,
+        );
+
+        Ok(())
+    }
+
+    /// Takes two constants (a, b) and returns an allocated number
+    /// equal to a if condition is false, and b otherwise.
+    pub fn conditionally_select_constant<CS>(
+        mut cs: CS,
+        a: Scalar,
+        b: Scalar,
+        condition: &Boolean,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        match *condition {
+            Boolean::Constant(false) => return Self::alloc_constant(cs, a),
+            Boolean::Constant(true) => return Self::alloc_constant(cs, b),
+            _ => {}
+        }
+
+        let c = Self::alloc(&mut cs.namespace(|| "alloc output"), || {
+            if condition
+                .get_value()
+                .ok_or(SynthesisError::AssignmentMissing)?
+            {
+                Ok(b)
+            } else {
+                Ok(a)
+            }
+        })?;
+
+        // Constrain: condition * (b - a) = c - a
+        cs.enforce(
+            || "condition * (b - a) === c - a",
+            |_| condition.lc(CS::one(), b - a),
+            |lc| lc + CS::one(),
+            |lc| lc + c.variable - (a, CS::one()),
+        );
+
+        Ok(c)
+    }
+
     /// Takes two allocated numbers (a, b) and returns
     /// (b, a) if the condition is true, and (a, b)
     /// otherwise.
@@ -594,6 +1991,13 @@ impl<Scalar: PrimeField> AllocatedNum<Scalar> {
     where
         CS: ConstraintSystem<Scalar>,
     {
+        // A constant condition reverses (or not) without any constraints.
+        match *condition {
+            Boolean::Constant(false) => return Ok((*a, *b)),
+            Boolean::Constant(true) => return Ok((*b, *a)),
+            _ => {}
+        }
+
         let c = Self::alloc(cs.namespace(|| "conditional reversal result 1"), || {
             if condition
                 .get_value()
@@ -633,14 +2037,241 @@ impl<Scalar: PrimeField> AllocatedNum<Scalar> {
         Ok((c, d))
     }
 
-    /// Builds a mux tree. The first bit is taken as the highest order.
-    // Code Adapted from https://github.com/alex-ozdemir/bellman-bignat/blob/0e10f9f7ef4a061deaf4d7684d398dca613174c8/src/util/gadget.rs#L124
-    pub fn mux_tree<'a, CS>(
-        cs: &mut CS,
-        mut select_bits: impl Iterator<Item = &'a Boolean> + Clone,
-        inputs: &[Self],
-    ) -> Result<Self, SynthesisError>
-    where
+    /// Orders a node and its sibling for hashing one level up a Merkle path.
+    /// `is_right` is the path bit saying whether `current` is the right
+    /// child; the result is `(left, right)`, i.e. `(sibling, current)` when
+    /// it is set and `(current, sibling)` otherwise. Built on
+    /// `conditionally_reverse`, so it costs two constraints for an
+    /// allocated bit and none for a constant one.
+    pub fn order_siblings<CS>(
+        cs: CS,
+        current: &Self,
+        sibling: &Self,
+        is_right: &Boolean,
+    ) -> Result<(Self, Self), SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        Self::conditionally_reverse(cs, current, sibling, is_right)
+    }
+
+    /// Returns `(selected, other)`: the number `conditionally_select` would
+    /// pick for `condition`, and the one it would discard. That is `(a, b)`
+    /// when `condition` is false and `(b, a)` when it is true. Both outputs
+    /// come from a single `conditionally_reverse`, which is cheaper than two
+    /// separate selects.
+    pub fn partition<CS>(
+        cs: CS,
+        a: &Self,
+        b: &Self,
+        condition: &Boolean,
+    ) -> Result<(Self, Self), SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        Self::conditionally_reverse(cs, a, b, condition)
+    }
+
+    /// Enforces `nums[i] < nums[i + 1]` for every adjacent pair, with each
+    /// number assumed to be range-checked to `n` bits as for
+    /// `is_less_than`. Empty and single-element slices add no constraints.
+    pub fn enforce_strictly_increasing<CS>(
+        mut cs: CS,
+        nums: &[Self],
+        n: usize,
+    ) -> Result<(), SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+        Scalar: PrimeFieldBits,
+    {
+        for (i, pair) in nums.windows(2).enumerate() {
+            let mut cs = cs.namespace(|| format!("pair {i}"));
+            let lt = Self::is_less_than(cs.namespace(|| "less than"), &pair[0], &pair[1], n)?;
+            Boolean::enforce_equal(cs.namespace(|| "increasing"), &lt, &Boolean::constant(true))?;
+        }
+
+        Ok(())
+    }
+
+    /// Applies `relation` to every run of `window` consecutive numbers in
+    /// `nums`, each in its own namespace `window {i}`. Sequences shorter
+    /// than `window` add no constraints.
+    pub fn enforce_window<CS, F>(
+        mut cs: CS,
+        nums: &[Self],
+        window: usize,
+        mut relation: F,
+    ) -> Result<(), SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+        F: FnMut(Namespace<'_, Scalar, CS::Root>, &[Self]) -> Result<(), SynthesisError>,
+    {
+        assert!(window > 0);
+
+        for (i, nums) in nums.windows(window).enumerate() {
+            relation(cs.namespace(|| format!("window {i}")), nums)?;
+        }
+
+        Ok(())
+    }
+
+    /// Sorts `nums` in ascending order with Batcher's merge-exchange
+    /// network. Every comparator is an `is_less_than` followed by a
+    /// `conditionally_reverse`, so the output is fully constrained to be a
+    /// sorted permutation of the input. As with `is_less_than`, each number
+    /// must already be range-checked to `n` bits.
+    pub fn sort<CS>(mut cs: CS, nums: &[Self], n: usize) -> Result<Vec<Self>, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+        Scalar: PrimeFieldBits,
+    {
+        let mut sorted = nums.to_vec();
+        let len = sorted.len();
+        if len < 2 {
+            return Ok(sorted);
+        }
+
+        // Knuth, TAOCP Vol. 3, 5.2.2, Algorithm M.
+        let t = usize::BITS - (len - 1).leading_zeros();
+        let mut comparator = 0;
+        let mut p = 1 << (t - 1);
+        while p > 0 {
+            let (mut q, mut r, mut d) = (1 << (t - 1), 0, p);
+            loop {
+                for i in 0..len - d {
+                    if i & p != r {
+                        continue;
+                    }
+
+                    let mut cs = cs.namespace(|| format!("comparator {comparator}"));
+                    let swap =
+                        Self::is_less_than(cs.namespace(|| "swap"), &sorted[i + d], &sorted[i], n)?;
+                    let (min, max) = Self::conditionally_reverse(
+                        cs.namespace(|| "min max"),
+                        &sorted[i],
+                        &sorted[i + d],
+                        &swap,
+                    )?;
+                    sorted[i] = min;
+                    sorted[i + d] = max;
+                    comparator += 1;
+                }
+
+                if q == p {
+                    break;
+                }
+                d = q - p;
+                q >>= 1;
+                r = p;
+            }
+            p >>= 1;
+        }
+
+        Ok(sorted)
+    }
+
+    /// Selects `inputs[2 * b1 + b0]`, writing the output as the
+    /// multilinear function of the bits
+    /// `a0 + b0 (a1 - a0) + b1 ((a2 - a0) + b0 (a3 - a2 - a1 + a0))`.
+    /// With two allocated bits this takes three constraints, as each of the
+    /// `b0 * _`, `b1 * _` and `b0 * b1 * _` terms needs its own product.
+    /// A constant bit is folded away, so the remaining selection costs a
+    /// single constraint where `mux_tree` still selects in both halves.
+    pub fn select4<CS>(
+        mut cs: CS,
+        inputs: &[Self; 4],
+        b1: &Boolean,
+        b0: &Boolean,
+    ) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        let [a0, a1, a2, a3] = inputs;
+
+        match (b1, b0) {
+            (&Boolean::Constant(b1), _) => {
+                let (lo, hi) = if b1 { (a2, a3) } else { (a0, a1) };
+                return Self::conditionally_select(cs, lo, hi, b0);
+            }
+            (_, &Boolean::Constant(b0)) => {
+                let (lo, hi) = if b0 { (a1, a3) } else { (a0, a2) };
+                return Self::conditionally_select(cs, lo, hi, b1);
+            }
+            _ => {}
+        }
+
+        // Witnesses for m = b0 (a3 - a2 - a1 + a0), t = b1 ((a2 - a0) + m)
+        // and the selected output.
+        let witness = match (
+            b1.get_value(),
+            b0.get_value(),
+            inputs.iter().map(|a| a.value).collect::<Option<Vec<_>>>(),
+        ) {
+            (Some(b1), Some(b0), Some(a)) => {
+                let m = if b0 {
+                    a[3] - a[2] - a[1] + a[0]
+                } else {
+                    Scalar::ZERO
+                };
+                let t = if b1 { a[2] - a[0] + m } else { Scalar::ZERO };
+                Some((m, t, a[usize::from(b1) * 2 + usize::from(b0)]))
+            }
+            _ => None,
+        };
+
+        let m = cs.alloc(
+            || "m",
+            || {
+                witness
+                    .map(|(m, _, _)| m)
+                    .ok_or(SynthesisError::AssignmentMissing)
+            },
+        )?;
+        cs.enforce(
+            || "m constraint",
+            |_| b0.lc(CS::one(), Scalar::ONE),
+            |lc| lc + a3.variable - a2.variable - a1.variable + a0.variable,
+            |lc| lc + m,
+        );
+
+        let t = cs.alloc(
+            || "t",
+            || {
+                witness
+                    .map(|(_, t, _)| t)
+                    .ok_or(SynthesisError::AssignmentMissing)
+            },
+        )?;
+        cs.enforce(
+            || "t constraint",
+            |_| b1.lc(CS::one(), Scalar::ONE),
+            |lc| lc + a2.variable - a0.variable + m,
+            |lc| lc + t,
+        );
+
+        let out = Self::alloc(cs.namespace(|| "out"), || {
+            witness
+                .map(|(_, _, out)| out)
+                .ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        cs.enforce(
+            || "out constraint",
+            |_| b0.lc(CS::one(), Scalar::ONE),
+            |lc| lc + a1.variable - a0.variable,
+            |lc| lc + out.variable - a0.variable - t,
+        );
+
+        Ok(out)
+    }
+
+    /// Builds a mux tree. The first bit is taken as the highest order.
+    // Code Adapted from https://github.com/alex-ozdemir/bellman-bignat/blob/0e10f9f7ef4a061deaf4d7684d398dca613174c8/src/util/gadget.rs#L124
+    pub fn mux_tree<'a, CS>(
+        cs: &mut CS,
+        mut select_bits: impl Iterator<Item = &'a Boolean> + Clone,
+        inputs: &[Self],
+    ) -> Result<Self, SynthesisError>
+    where
         CS: ConstraintSystem<Scalar>,
     {
         if let Some(bit) = select_bits.next() {
@@ -661,13 +2292,218 @@ impl<Scalar: PrimeField> AllocatedNum<Scalar> {
         }
     }
 
+    /// Enforces `bits[i] => bits[i + 1]` for every adjacent pair, so that
+    /// once a bit in the chain is set all following bits are set too
+    /// (a thermometer encoding).
+    pub fn enforce_boolean_implication_chain<CS>(
+        mut cs: CS,
+        bits: &[Boolean],
+    ) -> Result<(), SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        for (i, pair) in bits.windows(2).enumerate() {
+            match (&pair[0], &pair[1]) {
+                // false => x and x => true always hold
+                (&Boolean::Constant(false), _) | (_, &Boolean::Constant(true)) => {}
+                (&Boolean::Constant(true), &Boolean::Constant(false)) => {
+                    return Err(SynthesisError::Unsatisfiable);
+                }
+                (a, b) => {
+                    // Constrain: a * (1 - b) = 0
+                    cs.enforce(
+                        || format!("bit {} implies bit {}", i, i + 1),
+                        |_| a.lc(CS::one(), Scalar::ONE),
+                        |_| b.not().lc(CS::one(), Scalar::ONE),
+                        |lc| lc,
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Enforces that `output` is a permutation of `input`. The permutation
+    /// is witnessed as an `n x n` boolean matrix `perm` in row-major order,
+    /// where `perm[i * n + j]` is set iff `output[i] == input[j]`. Every row
+    /// and every column of the matrix must contain exactly one set bit.
+    pub fn enforce_permutation<CS>(
+        mut cs: CS,
+        input: &[Self],
+        output: &[Self],
+        perm: &[Boolean],
+    ) -> Result<(), SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        let n = input.len();
+        if output.len() != n {
+            return Err(SynthesisError::IncompatibleLengthVector(format!(
+                "permutation input has length {} but output has length {}",
+                n,
+                output.len()
+            )));
+        }
+        if perm.len() != n * n {
+            return Err(SynthesisError::IncompatibleLengthVector(format!(
+                "permutation matrix has {} entries, expected {}",
+                perm.len(),
+                n * n
+            )));
+        }
+
+        for i in 0..n {
+            let row = &perm[i * n..(i + 1) * n];
+
+            // Constrain: sum_j perm[i][j] = 1
+            cs.enforce(
+                || format!("row {} sums to one", i),
+                |lc| {
+                    row.iter()
+                        .fold(lc, |lc, bit| lc + &bit.lc(CS::one(), Scalar::ONE))
+                },
+                |lc| lc + CS::one(),
+                |lc| lc + CS::one(),
+            );
+
+            // Constrain: sum_i perm[i][j] = 1
+            cs.enforce(
+                || format!("column {} sums to one", i),
+                |lc| (0..n).fold(lc, |lc, j| lc + &perm[j * n + i].lc(CS::one(), Scalar::ONE)),
+                |lc| lc + CS::one(),
+                |lc| lc + CS::one(),
+            );
+        }
+
+        // Since each row has exactly one set bit, enforcing
+        // perm[i][j] * (input[j] - output[i]) = 0 pins output[i]
+        // to the selected input.
+        for (i, out) in output.iter().enumerate() {
+            for (j, inp) in input.iter().enumerate() {
+                cs.enforce(
+                    || format!("output {} matches input {} if selected", i, j),
+                    |_| perm[i * n + j].lc(CS::one(), Scalar::ONE),
+                    |lc| lc + inp.variable - out.variable,
+                    |lc| lc,
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Evaluates the polynomial `coeffs[0] + coeffs[1] * x + ...` at `x`
+    /// using Horner's method, with a single constraint per degree.
+    pub fn eval_poly<CS>(mut cs: CS, coeffs: &[Self], x: &Self) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        let (last, rest) = match coeffs.split_last() {
+            Some(split) => split,
+            None => return Self::alloc_constant(cs.namespace(|| "zero"), Scalar::ZERO),
+        };
+
+        let mut acc = *last;
+        for (i, coeff) in rest.iter().enumerate().rev() {
+            let mut value = None;
+
+            let var = cs.alloc(
+                || format!("horner step {}", i),
+                || {
+                    let mut tmp = acc.value.ok_or(SynthesisError::AssignmentMissing)?;
+                    tmp.mul_assign(x.value.ok_or(SynthesisError::AssignmentMissing)?);
+                    tmp.add_assign(coeff.value.ok_or(SynthesisError::AssignmentMissing)?);
+
+                    value = Some(tmp);
+
+                    Ok(tmp)
+                },
+            )?;
+
+            // Constrain: acc * x = var - coeff
+            cs.enforce(
+                || format!("horner step {} constraint", i),
+                |lc| lc + acc.variable,
+                |lc| lc + x.variable,
+                |lc| lc + var - coeff.variable,
+            );
+
+            acc = AllocatedNum {
+                value,
+                variable: var,
+            };
+        }
+
+        Ok(acc)
+    }
+
     pub fn get_value(&self) -> Option<Scalar> {
         self.value
     }
 
+    /// Returns the value, or `SynthesisError::AssignmentMissing` if it is
+    /// not known.
+    pub fn value_or_missing(&self) -> Result<Scalar, SynthesisError> {
+        self.value.ok_or(SynthesisError::AssignmentMissing)
+    }
+
     pub fn get_variable(&self) -> Variable {
         self.variable
     }
+
+    /// Returns the witness value of this number, detached from its
+    /// `Variable`, for caching.
+    pub fn witness_only(&self) -> WitnessOnly<Scalar> {
+        WitnessOnly::from(self)
+    }
+}
+
+/// Allocates the `n` little-endian bits of `value` and enforces that they
+/// pack to `lc`, proving that `lc` evaluates to an `n`-bit number.
+fn alloc_packed_bits_le<Scalar, CS>(
+    mut cs: CS,
+    value: Option<Scalar>,
+    lc: LinearCombination<Scalar>,
+    n: usize,
+) -> Result<Vec<AllocatedBit>, SynthesisError>
+where
+    Scalar: PrimeFieldBits,
+    CS: ConstraintSystem<Scalar>,
+{
+    assert!(n <= Scalar::CAPACITY as usize);
+
+    let values = match value {
+        Some(value) => {
+            let bits = value.to_le_bits();
+            bits.iter().take(n).map(|b| Some(*b)).collect()
+        }
+        None => vec![None; n],
+    };
+
+    let bits = values
+        .into_iter()
+        .enumerate()
+        .map(|(i, b)| AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), b))
+        .collect::<Result<Vec<_>, SynthesisError>>()?;
+
+    let mut packed = LinearCombination::zero();
+    let mut coeff = Scalar::ONE;
+
+    for bit in bits.iter() {
+        packed = packed + (coeff, bit.get_variable());
+
+        coeff = coeff.double();
+    }
+
+    cs.enforce(
+        || "packing constraint",
+        |_| packed,
+        |lc| lc + CS::one(),
+        |_| lc,
+    );
+
+    Ok(bits)
 }
 
 #[derive(Debug, Clone)]
@@ -685,489 +2521,2481 @@ impl<Scalar: PrimeField> From<AllocatedNum<Scalar>> for Num<Scalar> {
     }
 }
 
+impl<Scalar: PrimeField> Sum<AllocatedNum<Scalar>> for Num<Scalar> {
+    fn sum<I: Iterator<Item = AllocatedNum<Scalar>>>(iter: I) -> Self {
+        iter.fold(Num::zero(), |acc, num| acc.add(&Num::from(num)))
+    }
+}
+
+impl<Scalar: PrimeField> FromIterator<AllocatedNum<Scalar>> for Num<Scalar> {
+    /// Collects the numbers into their sum, like `Sum`.
+    fn from_iter<I: IntoIterator<Item = AllocatedNum<Scalar>>>(iter: I) -> Self {
+        iter.into_iter().sum()
+    }
+}
+
 impl<Scalar: PrimeField> Num<Scalar> {
     pub fn zero() -> Self {
         Num {
             value: Some(Scalar::ZERO),
             lc: LinearCombination::zero(),
         }
-    }
+    }
+
+    pub fn get_value(&self) -> Option<Scalar> {
+        self.value
+    }
+
+    /// Returns the value, or `SynthesisError::AssignmentMissing` if it is
+    /// not known.
+    pub fn value_or_missing(&self) -> Result<Scalar, SynthesisError> {
+        self.value.ok_or(SynthesisError::AssignmentMissing)
+    }
+
+    pub fn lc(&self, coeff: Scalar) -> LinearCombination<Scalar> {
+        LinearCombination::zero() + (coeff, &self.lc)
+    }
+
+    /// Adds `operands`, each assumed to fit in `width` bits, and decomposes
+    /// the sum into its low `width` result bits and the carry bits above
+    /// them, little-endian. The carry takes `ceil(log2(operands.len()))`
+    /// bits, enough for the largest possible sum, and the decomposition is
+    /// enforced with a single packing constraint. `UInt32::addmany` does not
+    /// use this, as it batches its packing constraints through `MultiEq`.
+    #[allow(clippy::type_complexity)]
+    pub fn add_with_carry<CS>(
+        cs: CS,
+        operands: &[Self],
+        width: usize,
+    ) -> Result<(Vec<Boolean>, Vec<Boolean>), SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+        Scalar: PrimeFieldBits,
+    {
+        let carry_width = match operands.len() {
+            0 | 1 => 0,
+            k => (usize::BITS - (k - 1).leading_zeros()) as usize,
+        };
+
+        let sum = operands
+            .iter()
+            .fold(Num::zero(), |acc, operand| acc.add(operand));
+
+        let bits = alloc_packed_bits_le(cs, sum.value, sum.lc, width + carry_width)?;
+        let mut bits = bits.into_iter().map(Boolean::from).collect::<Vec<_>>();
+        let carry = bits.split_off(width);
+
+        Ok((bits, carry))
+    }
+
+    pub fn add_bool_with_coeff(self, one: Variable, bit: &Boolean, coeff: Scalar) -> Self {
+        let newval = match (self.value, bit.get_value()) {
+            (Some(mut curval), Some(bval)) => {
+                if bval {
+                    curval.add_assign(&coeff);
+                }
+
+                Some(curval)
+            }
+            _ => None,
+        };
+
+        Num {
+            value: newval,
+            lc: self.lc + &bit.lc(one, coeff),
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn add(self, other: &Self) -> Self {
+        let lc = self.lc + &other.lc;
+        let value = match (self.value, other.value) {
+            (Some(v1), Some(v2)) => {
+                let mut tmp = v1;
+                tmp.add_assign(&v2);
+                Some(tmp)
+            }
+            _ => None,
+        };
+
+        Num { value, lc }
+    }
+
+    pub fn scale(mut self, scalar: Scalar) -> Self {
+        for (_variable, fr) in self.lc.iter_mut() {
+            fr.mul_assign(&scalar);
+        }
+
+        if let Some(ref mut v) = self.value {
+            v.mul_assign(&scalar);
+        }
+
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::ops::{AddAssign, MulAssign, Neg, SubAssign};
+
+    use crate::{ConstraintSystem, LinearCombination, SynthesisError};
+    use blstrs::Scalar as Fr;
+    use ff::{Field, PrimeField, PrimeFieldBits};
+    use rand_core::{RngCore, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    use super::{AllocatedNum, AllocatedNumHex, Boolean, Num, WitnessOnly};
+    use crate::util_cs::test_cs::*;
+
+    #[test]
+    fn test_allocated_num() {
+        let mut cs = TestConstraintSystem::<Fr>::new();
+
+        AllocatedNum::alloc(&mut cs, || Ok(Fr::ONE)).unwrap();
+
+        assert!(cs.get("num") == Fr::ONE);
+    }
+
+    #[test]
+    fn test_allocated_num_alloc_value() {
+        let mut cs = TestConstraintSystem::<Fr>::new();
+
+        let n = AllocatedNum::alloc_value(cs.namespace(|| "a"), Some(Fr::ONE)).unwrap();
+        assert_eq!(n.get_value(), Some(Fr::ONE));
+        assert_eq!(cs.get("a/num"), Fr::ONE);
+
+        assert!(matches!(
+            AllocatedNum::alloc_value(cs.namespace(|| "b"), None),
+            Err(SynthesisError::AssignmentMissing)
+        ));
+    }
+
+    #[test]
+    fn test_allocated_num_hash_by_variable() {
+        use std::collections::HashSet;
+
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::ONE)).unwrap();
+        let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::ONE)).unwrap();
+
+        // Same value, different variables.
+        assert_ne!(a, b);
+
+        let set: HashSet<_> = [a, a, b, b, a].into_iter().collect();
+        assert_eq!(set.len(), 2);
+        assert!(set.contains(&a));
+        assert!(set.contains(&b));
+    }
+
+    #[test]
+    fn test_allocated_infallible_num() {
+        let mut cs = TestConstraintSystem::<Fr>::new();
+
+        AllocatedNum::alloc_infallible(&mut cs, || Fr::ONE);
+
+        assert!(cs.get("num") == Fr::ONE);
+    }
+
+    #[test]
+    fn test_allocated_input_one() {
+        let mut cs = TestConstraintSystem::<Fr>::new();
+
+        let one = AllocatedNum::alloc_input_one(&mut cs).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert!(cs.verify(&[Fr::ONE]));
+        assert_eq!(one.get_value(), Some(Fr::ONE));
+        assert_eq!(cs.get("one/input num"), Fr::ONE);
+
+        cs.set("one/input num", Fr::from(2u64));
+        assert_eq!(cs.which_is_unsatisfied(), Some("one constraint"));
+    }
+
+    #[test]
+    fn test_alloc_from_le_bytes() {
+        let value = 0x0123_4567_89ab_cdefu64;
+        let bytes = value.to_le_bytes();
+
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let (num, bits) = AllocatedNum::alloc_from_le_bytes(&mut cs, &bytes).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(num.get_value(), Some(Fr::from(value)));
+        assert_eq!(bits.len(), 64);
+        for (i, bit) in bits.iter().enumerate() {
+            assert_eq!(bit.get_value(), Some((value >> i) & 1 == 1));
+        }
+
+        cs.set("bits/bit 0/boolean", Fr::ZERO);
+        assert_eq!(cs.which_is_unsatisfied(), Some("bits/packing constraint"));
+
+        // 31 bytes fit in the capacity of the field, 32 bytes do not.
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let (num, _) = AllocatedNum::alloc_from_le_bytes(&mut cs, &[0xff; 31]).unwrap();
+        assert!(cs.is_satisfied());
+        assert_eq!(
+            num.get_value(),
+            Some(Fr::from(2).pow_vartime([248]) - Fr::ONE)
+        );
+
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        assert!(matches!(
+            AllocatedNum::alloc_from_le_bytes(&mut cs, &[0; 32]),
+            Err(SynthesisError::IncompatibleLengthVector(_))
+        ));
+    }
+
+    #[test]
+    fn test_num_partial_addition() {
+        let a = Num::<Fr>::zero();
+        let b = Num::<Fr> {
+            value: None,
+            lc: Default::default(),
+        };
+        let c = a.clone().add(&b);
+        assert!(c.value.is_none());
+        let c = b.clone().add(&a);
+        assert!(c.value.is_none());
+        let c = b.clone().add(&b);
+        assert!(c.value.is_none());
+        let c = a.clone().add(&a);
+        assert!(c.value == Some(Fr::ZERO));
+    }
+
+    #[test]
+    fn test_num_addition() {
+        let mut cs = TestConstraintSystem::<Fr>::new();
+
+        let mut char = Fr::char();
+        char[0] -= 1u8;
+        let mod_minus_one = Fr::from_repr(char);
+        assert!(bool::from(mod_minus_one.is_some()));
+        let mod_minus_one = mod_minus_one.unwrap();
+
+        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(mod_minus_one)).unwrap();
+        let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::ONE)).unwrap();
+        let c = a.add(&mut cs, &b).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert!(cs.get("sum num") == Fr::ZERO);
+        assert!(c.value.unwrap() == Fr::ZERO);
+        cs.set("sum num", Fr::ONE);
+        assert!(!cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_num_conditionally_increment() {
+        use super::AllocatedBit;
+
+        for flag in [false, true] {
+            let expected = if flag { Fr::from(8u64) } else { Fr::from(7u64) };
+
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from(7u64))).unwrap();
+            let bit = AllocatedBit::alloc(cs.namespace(|| "flag"), Some(flag)).unwrap();
+            let b = a
+                .conditionally_increment(cs.namespace(|| "increment"), &Boolean::from(bit))
+                .unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(b.get_value().unwrap(), expected);
+            assert_eq!(cs.get("increment/incremented num"), expected);
+
+            cs.set("increment/incremented num", expected + Fr::ONE);
+            assert_eq!(
+                cs.which_is_unsatisfied(),
+                Some("increment/increment constraint")
+            );
+
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from(7u64))).unwrap();
+            let b = a
+                .conditionally_increment(cs.namespace(|| "increment"), &Boolean::constant(flag))
+                .unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(b.get_value().unwrap(), expected);
+            assert_eq!(b == a, !flag);
+            assert_eq!(cs.num_constraints(), usize::from(flag));
+        }
+    }
+
+    #[test]
+    fn test_num_subraction() {
+        let mut cs = TestConstraintSystem::<Fr>::new();
+
+        let mut char = Fr::char();
+        char[0] -= 1u8;
+        let mod_minus_one = Fr::from_repr(char);
+        assert!(bool::from(mod_minus_one.is_some()));
+        let mod_minus_one = mod_minus_one.unwrap();
+
+        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::ZERO)).unwrap();
+        let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::ONE)).unwrap();
+        let c = a.sub(&mut cs, &b).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert!(cs.get("sub num") == mod_minus_one);
+        assert!(c.value.unwrap() == mod_minus_one);
+        cs.set("sub num", Fr::ONE);
+        assert!(!cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_num_sub_checked() {
+        for (a_val, b_val) in [(200u64, 55u64), (255, 255), (255, 0), (3, 4), (0, 255)] {
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from(a_val))).unwrap();
+            let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::from(b_val))).unwrap();
+
+            let c = a.sub_checked(cs.namespace(|| "a - b"), &b, 8).unwrap();
+
+            assert_eq!(c.get_value().unwrap(), Fr::from(a_val) - Fr::from(b_val));
+            if a_val >= b_val {
+                assert!(cs.is_satisfied());
+            } else {
+                assert_eq!(
+                    cs.which_is_unsatisfied(),
+                    Some("a - b/range check/packing constraint")
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_num_shr_constant() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let n = 40;
+
+        for shift in [0, 1, 7, 16, 39, 40] {
+            let value = rng.next_u64() & ((1 << n) - 1);
+
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from(value))).unwrap();
+            let (q, r) = a
+                .shr_constant(cs.namespace(|| "a >> shift"), shift, n)
+                .unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(q.get_value().unwrap(), Fr::from(value >> shift));
+            assert_eq!(r.get_value().unwrap(), Fr::from(value & ((1 << shift) - 1)));
+
+            // Moving a unit from the quotient into the remainder breaks the
+            // remainder's range check.
+            if value >> shift > 0 {
+                let shifted = Fr::from(2).pow_vartime([shift as u64]);
+                cs.set("a >> shift/quotient/num", Fr::from((value >> shift) - 1));
+                cs.set(
+                    "a >> shift/remainder/num",
+                    Fr::from(value & ((1 << shift) - 1)) + shifted,
+                );
+                assert!(!cs.is_satisfied());
+            }
+        }
+
+        // Values wider than `n` bits fail the quotient range check.
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from(1u64 << n))).unwrap();
+        a.shr_constant(cs.namespace(|| "a >> 8"), 8, n).unwrap();
+        assert!(!cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_num_reduce_mod_constant() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for modulus in [1u64, 2, 7, 97, 256, 1000003] {
+            for _ in 0..10 {
+                let value = rng.next_u32() as u64;
+                let mut cs = TestConstraintSystem::<Fr>::new();
+                let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from(value))).unwrap();
+
+                let r = a
+                    .reduce_mod_constant(cs.namespace(|| "a mod m"), modulus, 32)
+                    .unwrap();
+
+                assert!(cs.is_satisfied());
+                assert_eq!(r.get_value().unwrap(), Fr::from(value % modulus));
+                assert_eq!(cs.get("a mod m/quotient/num"), Fr::from(value / modulus));
+            }
+        }
+
+        // A remainder which is not reduced fails the bound check: replace
+        // 100 = 20 * 5 + 0 with 100 = 19 * 5 + 5, where 5 still fits in the
+        // remainder's 3 bits.
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from(100u64))).unwrap();
+        a.reduce_mod_constant(cs.namespace(|| "a mod m"), 5, 8)
+            .unwrap();
+        assert!(cs.is_satisfied());
+
+        cs.set("a mod m/quotient/num", Fr::from(19u64));
+        cs.set("a mod m/quotient range check/bit 0/boolean", Fr::ONE);
+        cs.set("a mod m/quotient range check/bit 1/boolean", Fr::ONE);
+        cs.set("a mod m/quotient range check/bit 2/boolean", Fr::ZERO);
+        cs.set("a mod m/remainder/num", Fr::from(5u64));
+        cs.set("a mod m/remainder range check/bit 0/boolean", Fr::ONE);
+        cs.set("a mod m/remainder range check/bit 2/boolean", Fr::ONE);
+        assert_eq!(
+            cs.which_is_unsatisfied(),
+            Some("a mod m/remainder bound/packing constraint")
+        );
+    }
+
+    #[test]
+    fn test_num_negation() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let mut cs = TestConstraintSystem::<Fr>::new();
+
+        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::random(&mut rng))).unwrap();
+        let out = a.neg(&mut cs).unwrap();
+        let out_exp = a.get_value().unwrap().neg();
+
+        assert!(cs.is_satisfied());
+        assert!(cs.get("neg num") == out_exp);
+        assert!(out.value.unwrap() == out_exp);
+        cs.set("neg num", Fr::random(&mut rng));
+        assert!(!cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_num_squaring() {
+        let mut cs = TestConstraintSystem::<Fr>::new();
+
+        let n = AllocatedNum::alloc(&mut cs, || Ok(Fr::from(3u64))).unwrap();
+        let n2 = n.square(&mut cs).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert!(cs.get("squared num") == Fr::from(9u64));
+        assert!(n2.value.unwrap() == Fr::from(9u64));
+        cs.set("squared num", Fr::from(10u64));
+        assert!(!cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_num_pow5_pow7() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let x = Fr::random(&mut rng);
+
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let n = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(x)).unwrap();
+        let n5 = n.pow5(cs.namespace(|| "pow5")).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), 3);
+        assert_eq!(n5.get_value().unwrap(), x.pow_vartime([5]));
+        cs.set("pow5/x^4/squared num", Fr::ONE);
+        assert_eq!(
+            cs.which_is_unsatisfied(),
+            Some("pow5/x^4/squaring constraint")
+        );
+
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let n = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(x)).unwrap();
+        let n7 = n.pow7(cs.namespace(|| "pow7")).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), 4);
+        assert_eq!(n7.get_value().unwrap(), x.pow_vartime([7]));
+        cs.set("pow7/x^7/product num", Fr::ONE);
+        assert_eq!(
+            cs.which_is_unsatisfied(),
+            Some("pow7/x^7/multiplication constraint")
+        );
+    }
+
+    #[test]
+    fn test_num_multiplication() {
+        let mut cs = TestConstraintSystem::<Fr>::new();
+
+        let n = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from(12u64))).unwrap();
+        let n2 = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::from(10u64))).unwrap();
+        let n3 = n.mul(&mut cs, &n2).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert!(cs.get("product num") == Fr::from(120u64));
+        assert!(n3.value.unwrap() == Fr::from(120u64));
+        cs.set("product num", Fr::from(121u64));
+        assert!(!cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_num_enforce_product() {
+        let mut cs = TestConstraintSystem::<Fr>::new();
+
+        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from(12u64))).unwrap();
+        let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::from(10u64))).unwrap();
+        let c = AllocatedNum::alloc(cs.namespace(|| "c"), || Ok(Fr::from(120u64))).unwrap();
+        AllocatedNum::enforce_product(cs.namespace(|| "a * b = c"), &a, &b, &c).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), 1);
+
+        cs.set("c/num", Fr::from(121u64));
+        assert_eq!(
+            cs.which_is_unsatisfied(),
+            Some("a * b = c/product constraint")
+        );
+    }
+
+    #[test]
+    fn test_num_division() {
+        let mut cs = TestConstraintSystem::<Fr>::new();
+
+        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from(120u64))).unwrap();
+        let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::from(10u64))).unwrap();
+        let c = a.div(&mut cs, &b).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert!(cs.get("div num") == Fr::from(12u64));
+        assert!(c.value.unwrap() == Fr::from(12u64));
+        cs.set("div num", Fr::from(11u64));
+        assert!(!cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_num_is_zero() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        {
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::random(&mut rng))).unwrap();
+            let is_zero = a.is_zero(&mut cs).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert!(cs.get("out bit/boolean") == Fr::from(0u64));
+            assert!(!is_zero.get_value().unwrap());
+            cs.set("out bit/boolean", Fr::from(1u64));
+            assert!(!cs.is_satisfied());
+        }
+
+        {
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::ZERO)).unwrap();
+            let is_zero = a.is_zero(&mut cs).unwrap();
+            assert!(cs.is_satisfied());
+            assert!(cs.get("out bit/boolean") == Fr::from(1u64));
+            assert!(is_zero.get_value().unwrap());
+            cs.set("out bit/boolean", Fr::from(0u64));
+            assert!(!cs.is_satisfied());
+        }
+    }
+
+    #[test]
+    fn test_num_is_zero_many() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let values = [
+            Fr::random(&mut rng),
+            Fr::ZERO,
+            Fr::ONE,
+            Fr::ZERO,
+            Fr::random(&mut rng),
+        ];
+
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let nums = values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| AllocatedNum::alloc(cs.namespace(|| format!("num {i}")), || Ok(*v)))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let bits = AllocatedNum::is_zero_many(cs.namespace(|| "is zero many"), &nums).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(bits.len(), nums.len());
+        assert_eq!(cs.num_constraints(), 3 * nums.len());
+
+        for (i, (num, bit)) in nums.iter().zip(&bits).enumerate() {
+            let expected = num
+                .is_zero(cs.namespace(|| format!("is zero {i}")))
+                .unwrap();
+            assert_eq!(bit.get_value(), expected.get_value());
+            assert_eq!(bit.get_value(), Some(values[i] == Fr::ZERO));
+        }
+
+        assert!(cs.is_satisfied());
+        cs.set("is zero many/num 1/out bit/boolean", Fr::ZERO);
+        assert!(!cs.is_satisfied());
+        cs.set("is zero many/num 1/out bit/boolean", Fr::ONE);
+        cs.set("is zero many/num 4/out bit/boolean", Fr::ONE);
+        assert!(!cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_num_is_equal_with_diff() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let a_val = Fr::random(&mut rng);
+        let b_val = Fr::random(&mut rng);
+
+        for (a_val, b_val) in [(a_val, b_val), (a_val, a_val)] {
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(a_val)).unwrap();
+            let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(b_val)).unwrap();
+            let (is_equal, diff) = a.is_equal_with_diff(&mut cs, &b).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(diff.get_value().unwrap(), a_val - b_val);
+            assert_eq!(is_equal.get_value().unwrap(), a_val == b_val);
+
+            cs.set("self-other/sub num", a_val - b_val + Fr::ONE);
+            assert_eq!(
+                cs.which_is_unsatisfied(),
+                Some("self-other/subtraction constraint")
+            );
+        }
+    }
+
+    #[test]
+    fn test_num_is_any_nonzero() {
+        for (values, expected) in [
+            (vec![], false),
+            (vec![0u64], false),
+            (vec![0, 0, 0], false),
+            (vec![0, 5, 0], true),
+            (vec![0, 0, 1], true),
+            (vec![2, 3, 4], true),
+        ] {
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let nums = values
+                .iter()
+                .enumerate()
+                .map(|(i, v)| {
+                    AllocatedNum::alloc(cs.namespace(|| format!("a {}", i)), || Ok(Fr::from(*v)))
+                        .unwrap()
+                })
+                .collect::<Vec<_>>();
+
+            let any = AllocatedNum::is_any_nonzero(cs.namespace(|| "any"), &nums).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(any.get_value().unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_num_member_of() {
+        let set = [3u64, 7, 11, 13].map(Fr::from);
+
+        for (value, expected) in [(7u64, true), (13, true), (5, false)] {
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from(value))).unwrap();
+            a.enforce_member_of(cs.namespace(|| "enforce"), &set)
+                .unwrap();
+
+            assert_eq!(cs.num_constraints(), set.len() - 1);
+            if expected {
+                assert!(cs.is_satisfied());
+            } else {
+                assert_eq!(
+                    cs.which_is_unsatisfied(),
+                    Some("enforce/membership constraint")
+                );
+            }
+
+            let is_member = a.is_member_of(cs.namespace(|| "is member"), &set).unwrap();
+            assert_eq!(is_member.get_value().unwrap(), expected);
+            if expected {
+                assert!(cs.is_satisfied());
+            }
+        }
+
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from(3u64))).unwrap();
+        a.enforce_member_of(cs.namespace(|| "single"), &set[..1])
+            .unwrap();
+        assert!(cs.is_satisfied());
+        assert!(matches!(
+            a.enforce_member_of(cs.namespace(|| "empty"), &[]),
+            Err(SynthesisError::Unsatisfiable)
+        ));
+        let is_member = a.is_member_of(cs.namespace(|| "is member"), &[]).unwrap();
+        assert!(!is_member.get_value().unwrap());
+    }
+
+    #[test]
+    fn test_num_index_equals() {
+        for (value, in_range) in [(5u64, true), (3, true), (8, true), (10, false)] {
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from(value))).unwrap();
+
+            let bits = a.index_equals_all(cs.namespace(|| "decode"), 3..9).unwrap();
+            assert_eq!(bits.len(), 6);
+            assert_eq!(cs.is_satisfied(), in_range);
+
+            for (bit, k) in bits.iter().zip(3u64..9) {
+                assert_eq!(bit.get_value().unwrap(), value == k);
+
+                let single = a
+                    .index_equals(cs.namespace(|| format!("equals {k}")), k)
+                    .unwrap();
+                assert_eq!(single.get_value(), bit.get_value());
+            }
+
+            if in_range {
+                assert!(cs.is_satisfied());
+
+                // Moving the hot bit breaks the weighted sum.
+                let k = if value == 3 { 4 } else { 3 };
+                cs.set(&format!("decode/index {value}/boolean"), Fr::ZERO);
+                cs.set(&format!("decode/index {k}/boolean"), Fr::ONE);
+                assert_eq!(
+                    cs.which_is_unsatisfied(),
+                    Some("decode/weighted sum constraint")
+                );
+            }
+        }
+
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from(5u64))).unwrap();
+        let is_five = a.index_equals(cs.namespace(|| "equals 5"), 5).unwrap();
+        assert!(is_five.get_value().unwrap());
+        cs.set("equals 5/out bit/boolean", Fr::ZERO);
+        assert!(!cs.is_satisfied());
+        assert!(matches!(
+            a.index_equals_all(cs.namespace(|| "empty"), 3..3),
+            Err(SynthesisError::Unsatisfiable)
+        ));
+    }
+
+    #[test]
+    fn test_num_enforce_boolean() {
+        for value in [0u64, 1] {
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from(value))).unwrap();
+            let bit = a.enforce_boolean(cs.namespace(|| "bit")).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(cs.num_constraints(), 1);
+            assert_eq!(bit.get_value().unwrap(), value == 1);
+            assert_eq!(
+                bit.lc::<Fr>(TestConstraintSystem::<Fr>::one(), Fr::ONE)
+                    .iter()
+                    .map(|(var, _)| var)
+                    .collect::<Vec<_>>(),
+                vec![a.get_variable()]
+            );
+        }
+
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from(2u64))).unwrap();
+        a.enforce_boolean(cs.namespace(|| "bit")).unwrap();
+        assert_eq!(cs.which_is_unsatisfied(), Some("bit/boolean constraint"));
+    }
+
+    #[test]
+    fn test_num_is_equal() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        {
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::random(&mut rng))).unwrap();
+            let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::random(&mut rng))).unwrap();
+            let is_equal = a.is_equal(&mut cs, &b).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert!(cs.get("out bit/boolean") == Fr::from(0u64));
+            assert!(!is_equal.get_value().unwrap());
+            cs.set("out bit/boolean", Fr::from(1u64));
+            assert!(!cs.is_satisfied());
+        }
+
+        {
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::random(&mut rng))).unwrap();
+            let b = a.clone();
+            let is_equal = a.is_equal(&mut cs, &b).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert!(cs.get("out bit/boolean") == Fr::from(1u64));
+            assert!(is_equal.get_value().unwrap());
+            cs.set("out bit/boolean", Fr::from(0u64));
+            assert!(!cs.is_satisfied());
+        }
+    }
+
+    #[test]
+    fn test_num_is_equal_constraints() {
+        for (a_val, b_val) in [(5u64, 5u64), (5, 6)] {
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from(a_val))).unwrap();
+            let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::from(b_val))).unwrap();
+
+            let is_equal = a.is_equal(cs.namespace(|| "is equal"), &b).unwrap();
+            let direct = cs.num_constraints();
+            let (with_diff, _) = a
+                .is_equal_with_diff(cs.namespace(|| "is equal with diff"), &b)
+                .unwrap();
+            let via_diff = cs.num_constraints() - direct;
+
+            assert!(cs.is_satisfied());
+            assert_eq!(is_equal.get_value(), Some(a_val == b_val));
+            assert_eq!(is_equal.get_value(), with_diff.get_value());
+            assert_eq!(direct, 3);
+            assert_eq!(via_diff, 4);
+        }
+    }
+
+    #[test]
+    fn test_num_enforce_equal_if() {
+        use super::AllocatedBit;
+
+        for (flag, a_val, b_val) in [(true, 5u64, 5u64), (false, 5, 5), (false, 5, 6)] {
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from(a_val))).unwrap();
+            let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::from(b_val))).unwrap();
+            let flag = AllocatedBit::alloc(cs.namespace(|| "flag"), Some(flag)).unwrap();
+
+            AllocatedNum::enforce_equal_if(cs.namespace(|| "a == b"), &flag.into(), &a, &b)
+                .unwrap();
+            assert!(cs.is_satisfied());
+
+            cs.set("flag/boolean", Fr::ONE);
+            assert_eq!(cs.is_satisfied(), a_val == b_val);
+        }
+
+        // A set flag with mismatched values fails during witness generation.
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from(5u64))).unwrap();
+        let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::from(6u64))).unwrap();
+        let flag = AllocatedBit::alloc(cs.namespace(|| "flag"), Some(true)).unwrap();
+        assert!(matches!(
+            AllocatedNum::enforce_equal_if(cs.namespace(|| "a == b"), &flag.into(), &a, &b),
+            Err(SynthesisError::Unsatisfiable)
+        ));
+        assert!(matches!(
+            AllocatedNum::enforce_equal_if(
+                cs.namespace(|| "constant"),
+                &Boolean::constant(true),
+                &a,
+                &b
+            ),
+            Err(SynthesisError::Unsatisfiable)
+        ));
+
+        let constraints = cs.num_constraints();
+        AllocatedNum::enforce_equal_if(
+            cs.namespace(|| "disabled"),
+            &Boolean::constant(false),
+            &a,
+            &b,
+        )
+        .unwrap();
+        assert_eq!(cs.num_constraints(), constraints);
+    }
+
+    #[test]
+    fn test_num_enforce_all_equal() {
+        let alloc_nums = |cs: &mut TestConstraintSystem<Fr>, values: &[u64]| {
+            values
+                .iter()
+                .enumerate()
+                .map(|(i, v)| {
+                    AllocatedNum::alloc(cs.namespace(|| format!("num {}", i)), || Ok(Fr::from(*v)))
+                        .unwrap()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        for values in [&[][..], &[5], &[5, 5, 5, 5]] {
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let nums = alloc_nums(&mut cs, values);
+            AllocatedNum::enforce_all_equal(&mut cs, &nums).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(cs.num_constraints(), values.len().saturating_sub(1));
+        }
+
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let nums = alloc_nums(&mut cs, &[5, 5, 6, 5]);
+        AllocatedNum::enforce_all_equal(&mut cs, &nums).unwrap();
+
+        assert_eq!(cs.which_is_unsatisfied(), Some("num 2 equals num 0"));
+    }
+
+    #[test]
+    fn test_num_is_less_than() {
+        for (a_val, b_val) in [(3u64, 5u64), (5, 3), (4, 4), (0, 255), (255, 0), (254, 255)] {
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from(a_val))).unwrap();
+            let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::from(b_val))).unwrap();
+            a.to_n_bits_le(cs.namespace(|| "a bits"), 8).unwrap();
+            b.to_n_bits_le(cs.namespace(|| "b bits"), 8).unwrap();
+
+            let lt = AllocatedNum::is_less_than(cs.namespace(|| "lt"), &a, &b, 8).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(lt.get_value().unwrap(), a_val < b_val);
+
+            // Flipping the top bit of the decomposition breaks the packing.
+            let top = cs.get("lt/a - b + 2^n/bit 8/boolean");
+            cs.set("lt/a - b + 2^n/bit 8/boolean", Fr::ONE - top);
+            assert_eq!(
+                cs.which_is_unsatisfied(),
+                Some("lt/a - b + 2^n/packing constraint")
+            );
+        }
+
+        // Values which do not fit in `n` bits fail the range check.
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from(256u64))).unwrap();
+        a.to_n_bits_le(cs.namespace(|| "a bits"), 8).unwrap();
+        assert_eq!(cs.which_is_unsatisfied(), Some("a bits/packing constraint"));
+    }
+
+    #[test]
+    fn test_num_abs_diff() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let n = 16;
+
+        let mut pairs = (0..20)
+            .map(|_| {
+                (
+                    rng.next_u32() as u64 & 0xffff,
+                    rng.next_u32() as u64 & 0xffff,
+                )
+            })
+            .collect::<Vec<_>>();
+        pairs.extend([(7, 7), (0, 0xffff), (0xffff, 0)]);
+
+        for (a_val, b_val) in pairs {
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from(a_val))).unwrap();
+            let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::from(b_val))).unwrap();
+
+            let diff = AllocatedNum::abs_diff(cs.namespace(|| "abs diff"), &a, &b, n).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(diff.get_value(), Some(Fr::from(a_val.abs_diff(b_val))));
+
+            // The wrong subtraction order wraps around and fails the range
+            // check.
+            if a_val != b_val {
+                cs.set("abs diff/hi - lo/sub num", -diff.get_value().unwrap());
+                assert!(!cs.is_satisfied());
+            }
+        }
+    }
+
+    #[test]
+    fn test_num_enforce_window() {
+        fn successor<CS: ConstraintSystem<Fr>>(
+            mut cs: CS,
+            window: &[AllocatedNum<Fr>],
+        ) -> Result<(), SynthesisError> {
+            // Constrain: window[1] = window[0] + 1
+            cs.enforce(
+                || "successor constraint",
+                |lc| lc + window[0].get_variable() + CS::one(),
+                |lc| lc + CS::one(),
+                |lc| lc + window[1].get_variable(),
+            );
+            Ok(())
+        }
+
+        for (values, satisfied) in [
+            (&[3u64, 4, 5, 6, 7][..], true),
+            (&[3, 4, 6, 7, 8], false),
+            (&[3], true),
+        ] {
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let nums = values
+                .iter()
+                .enumerate()
+                .map(|(i, v)| {
+                    AllocatedNum::alloc(cs.namespace(|| format!("num {i}")), || Ok(Fr::from(*v)))
+                        .unwrap()
+                })
+                .collect::<Vec<_>>();
+
+            AllocatedNum::enforce_window(cs.namespace(|| "sequence"), &nums, 2, |cs, window| {
+                successor(cs, window)
+            })
+            .unwrap();
+
+            assert_eq!(cs.is_satisfied(), satisfied);
+            assert_eq!(cs.num_constraints(), values.len() - 1);
+        }
+
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let nums = [1u64, 2, 3]
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                AllocatedNum::alloc(cs.namespace(|| format!("num {i}")), || Ok(Fr::from(*v)))
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        AllocatedNum::enforce_window(cs.namespace(|| "sequence"), &nums, 2, |cs, window| {
+            successor(cs, window)
+        })
+        .unwrap();
+        cs.set("num 1/num", Fr::from(5u64));
+        assert_eq!(
+            cs.which_is_unsatisfied(),
+            Some("sequence/window 0/successor constraint")
+        );
+    }
+
+    #[test]
+    fn test_num_enforce_strictly_increasing() {
+        let cases: [(&[u64], bool); 6] = [
+            (&[], true),
+            (&[7], true),
+            (&[0, 1, 5, 200, 255], true),
+            (&[0, 1, 1, 200], false),
+            (&[3, 2], false),
+            (&[0, 255, 254], false),
+        ];
+
+        for (values, expected) in cases {
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let nums = values
+                .iter()
+                .enumerate()
+                .map(|(i, v)| {
+                    let num = AllocatedNum::alloc(cs.namespace(|| format!("num {i}")), || {
+                        Ok(Fr::from(*v))
+                    })
+                    .unwrap();
+                    num.to_n_bits_le(cs.namespace(|| format!("num {i} bits")), 8)
+                        .unwrap();
+                    num
+                })
+                .collect::<Vec<_>>();
+
+            let constraints = cs.num_constraints();
+            AllocatedNum::enforce_strictly_increasing(cs.namespace(|| "increasing"), &nums, 8)
+                .unwrap();
+
+            assert_eq!(cs.is_satisfied(), expected);
+            if values.len() < 2 {
+                assert_eq!(cs.num_constraints(), constraints);
+            }
+        }
+    }
+
+    #[test]
+    fn test_num_sort() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for len in 0..10 {
+            let mut values = (0..len).map(|_| rng.next_u64() % 16).collect::<Vec<_>>();
+
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let nums = values
+                .iter()
+                .enumerate()
+                .map(|(i, v)| {
+                    let num = AllocatedNum::alloc(cs.namespace(|| format!("num {i}")), || {
+                        Ok(Fr::from(*v))
+                    })
+                    .unwrap();
+                    num.to_n_bits_le(cs.namespace(|| format!("num {i} bits")), 8)
+                        .unwrap();
+                    num
+                })
+                .collect::<Vec<_>>();
+
+            let sorted = AllocatedNum::sort(cs.namespace(|| "sort"), &nums, 8).unwrap();
+            assert!(cs.is_satisfied());
+
+            // The output is the native sort, hence a permutation of the input.
+            values.sort_unstable();
+            let expected = values.iter().map(|v| Fr::from(*v)).collect::<Vec<_>>();
+            let actual = sorted
+                .iter()
+                .map(|num| num.get_value().unwrap())
+                .collect::<Vec<_>>();
+            assert_eq!(actual, expected);
+
+            if len > 1 {
+                cs.set(
+                    "sort/comparator 0/min max/conditional reversal result 1/num",
+                    Fr::from(16u64),
+                );
+                assert!(!cs.is_satisfied());
+            }
+        }
+    }
+
+    #[test]
+    fn test_num_enforce_le_constant() {
+        let bound = Fr::from(1000u64);
+
+        for (value, expected) in [
+            (Fr::ZERO, true),
+            (Fr::from(999u64), true),
+            (bound, true),
+            (bound + Fr::ONE, false),
+            (Fr::from(1023u64), false),
+            (Fr::from(1024u64), false),
+            (-Fr::ONE, false),
+        ] {
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(value)).unwrap();
+            a.enforce_le_constant(cs.namespace(|| "a <= bound"), bound)
+                .unwrap();
+            assert_eq!(cs.is_satisfied(), expected);
+        }
+
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::ZERO)).unwrap();
+        a.enforce_le_constant(cs.namespace(|| "a <= 0"), Fr::ZERO)
+            .unwrap();
+        assert!(cs.is_satisfied());
+        cs.set("a/num", Fr::ONE);
+        assert!(!cs.is_satisfied());
+
+        // Bounds at or above 2^(CAPACITY - 1) use the canonical bits.
+        let large = Fr::from(2u64).pow_vartime([Fr::CAPACITY as u64 - 1]);
+        for (bound, value, expected) in [
+            (-Fr::ONE, -Fr::ONE, true),
+            (-Fr::ONE, Fr::ZERO, true),
+            (-Fr::from(10u64), -Fr::from(10u64), true),
+            (-Fr::from(10u64), -Fr::from(11u64), true),
+            (-Fr::from(10u64), Fr::from(5u64), true),
+            (-Fr::from(10u64), -Fr::from(9u64), false),
+            (-Fr::from(10u64), -Fr::ONE, false),
+            (large, large, true),
+            (large, large - Fr::ONE, true),
+            (large, large + Fr::ONE, false),
+            (large, -Fr::ONE, false),
+        ] {
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(value)).unwrap();
+            a.enforce_le_constant(cs.namespace(|| "a <= bound"), bound)
+                .unwrap();
+            assert_eq!(cs.is_satisfied(), expected);
+        }
+    }
+
+    #[test]
+    fn test_num_is_less_than_limbs() {
+        fn to_limbs(x: u64) -> Vec<u64> {
+            (0..4).map(|i| (x >> (16 * i)) & 0xffff).collect()
+        }
+
+        let cases = [
+            (0x0001_0000_0000_0000u64, 0x0002_0000_0000_0000u64),
+            (0x0002_0000_0000_0000, 0x0001_ffff_ffff_ffff),
+            (0x1234_5678_9abc_def0, 0x1234_5678_9abc_def0),
+            (0x1234_5678_9abc_def0, 0x1234_5678_9abc_def1),
+            (0x1234_5678_9abd_0000, 0x1234_5678_9abc_ffff),
+            (0, u64::MAX),
+            (u64::MAX, u64::MAX),
+        ];
+
+        for (a_val, b_val) in cases {
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let mut alloc_limbs = |name: &str, x: u64| {
+                to_limbs(x)
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, limb)| {
+                        AllocatedNum::alloc(cs.namespace(|| format!("{} {}", name, i)), || {
+                            Ok(Fr::from(limb))
+                        })
+                        .unwrap()
+                    })
+                    .collect::<Vec<_>>()
+            };
+            let a = alloc_limbs("a", a_val);
+            let b = alloc_limbs("b", b_val);
+
+            let lt = AllocatedNum::is_less_than_limbs(cs.namespace(|| "lt"), &a, &b, 16).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(lt.get_value().unwrap(), a_val < b_val);
+        }
+
+        // A limb exceeding `limb_bits` fails its range check.
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from(1u64 << 16))).unwrap();
+        let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::ONE)).unwrap();
+        AllocatedNum::is_less_than_limbs(cs.namespace(|| "lt"), &[a], &[b], 16).unwrap();
+        assert_eq!(
+            cs.which_is_unsatisfied(),
+            Some("lt/limb 0/a range check/packing constraint")
+        );
+    }
+
+    #[test]
+    fn test_num_conditional_constant_condition() {
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from(3u64))).unwrap();
+        let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::from(5u64))).unwrap();
+
+        for condition in [false, true] {
+            let condition = Boolean::constant(condition);
+            let (first, second) = if condition.get_value().unwrap() {
+                (b, a)
+            } else {
+                (a, b)
+            };
+
+            let c = AllocatedNum::conditionally_select(
+                cs.namespace(|| format!("select {:?}", condition)),
+                &a,
+                &b,
+                &condition,
+            )
+            .unwrap();
+            assert_eq!(c.get_variable(), first.get_variable());
+
+            let (c, d) = AllocatedNum::conditionally_reverse(
+                cs.namespace(|| format!("reverse {:?}", condition)),
+                &a,
+                &b,
+                &condition,
+            )
+            .unwrap();
+            assert_eq!(c.get_variable(), first.get_variable());
+            assert_eq!(d.get_variable(), second.get_variable());
+        }
+
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), 0);
+    }
+
+    #[test]
+    fn test_num_conditional_select() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        {
+            let mut cs = TestConstraintSystem::<Fr>::new();
+
+            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::random(&mut rng))).unwrap();
+            let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::random(&mut rng))).unwrap();
+            let condition = Boolean::constant(false);
+            let c = AllocatedNum::conditionally_select(&mut cs, &a, &b, &condition).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(a.value.unwrap(), c.value.unwrap());
+        }
+
+        {
+            let mut cs = TestConstraintSystem::<Fr>::new();
+
+            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::random(&mut rng))).unwrap();
+            let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::random(&mut rng))).unwrap();
+            let condition = Boolean::constant(true);
+            let c = AllocatedNum::conditionally_select(&mut cs, &a, &b, &condition).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(b.value.unwrap(), c.value.unwrap());
+        }
+    }
+
+    #[test]
+    fn test_num_enforce_select() {
+        use super::AllocatedBit;
+
+        let a_val = Fr::from(17u64);
+        let b_val = Fr::from(42u64);
+
+        for cond in [false, true] {
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(a_val)).unwrap();
+            let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(b_val)).unwrap();
+            let out = AllocatedNum::alloc(cs.namespace(|| "out"), || {
+                Ok(if cond { b_val } else { a_val })
+            })
+            .unwrap();
+            let bit = AllocatedBit::alloc(cs.namespace(|| "cond"), Some(cond)).unwrap();
+
+            AllocatedNum::enforce_select(cs.namespace(|| "select"), &out, &a, &b, &bit.into())
+                .unwrap();
+
+            assert!(cs.is_satisfied());
+            // One boolean constraint plus the selection constraint.
+            assert_eq!(cs.num_constraints(), 2);
+
+            cs.set("out/num", if cond { a_val } else { b_val });
+            assert_eq!(
+                cs.which_is_unsatisfied(),
+                Some("select/condition * (b - a) === out - a")
+            );
+        }
+    }
+
+    #[test]
+    fn test_num_conditional_select_constant() {
+        use super::AllocatedBit;
+
+        let a = Fr::from(17u64);
+        let b = Fr::from(42u64);
+
+        for cond in [false, true] {
+            let expected = if cond { b } else { a };
+
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let bit = AllocatedBit::alloc(cs.namespace(|| "cond"), Some(cond)).unwrap();
+            let c = AllocatedNum::conditionally_select_constant(
+                cs.namespace(|| "select"),
+                a,
+                b,
+                &Boolean::from(bit),
+            )
+            .unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(c.get_value().unwrap(), expected);
+            // One boolean constraint plus the selection constraint.
+            assert_eq!(cs.num_constraints(), 2);
+
+            cs.set("select/alloc output/num", if cond { a } else { b });
+            assert!(!cs.is_satisfied());
+
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let c = AllocatedNum::conditionally_select_constant(
+                cs.namespace(|| "select"),
+                a,
+                b,
+                &Boolean::constant(cond),
+            )
+            .unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(c.get_value().unwrap(), expected);
+            // A constant condition only pins the output to the chosen constant.
+            assert_eq!(cs.num_constraints(), 1);
+            assert_eq!(cs.get("select/alloc constant/num"), expected);
+
+            cs.set("select/alloc constant/num", if cond { a } else { b });
+            assert_eq!(
+                cs.which_is_unsatisfied(),
+                Some("select/constant constraint")
+            );
+        }
+    }
+
+    #[test]
+    fn test_num_conditional_reversal() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        {
+            let mut cs = TestConstraintSystem::<Fr>::new();
+
+            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::random(&mut rng))).unwrap();
+            let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::random(&mut rng))).unwrap();
+            let condition = Boolean::constant(false);
+            let (c, d) = AllocatedNum::conditionally_reverse(&mut cs, &a, &b, &condition).unwrap();
+
+            assert!(cs.is_satisfied());
+
+            assert_eq!(a.value.unwrap(), c.value.unwrap());
+            assert_eq!(b.value.unwrap(), d.value.unwrap());
+        }
+
+        {
+            let mut cs = TestConstraintSystem::<Fr>::new();
+
+            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::random(&mut rng))).unwrap();
+            let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::random(&mut rng))).unwrap();
+            let condition = Boolean::constant(true);
+            let (c, d) = AllocatedNum::conditionally_reverse(&mut cs, &a, &b, &condition).unwrap();
+
+            assert!(cs.is_satisfied());
+
+            assert_eq!(a.value.unwrap(), d.value.unwrap());
+            assert_eq!(b.value.unwrap(), c.value.unwrap());
+        }
+    }
+
+    #[test]
+    fn test_num_order_siblings() {
+        use super::AllocatedBit;
+
+        for is_right in [false, true] {
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let current =
+                AllocatedNum::alloc(cs.namespace(|| "current"), || Ok(Fr::from(1u64))).unwrap();
+            let sibling =
+                AllocatedNum::alloc(cs.namespace(|| "sibling"), || Ok(Fr::from(2u64))).unwrap();
+            let bit = Boolean::from(
+                AllocatedBit::alloc(cs.namespace(|| "is right"), Some(is_right)).unwrap(),
+            );
+
+            let (left, right) =
+                AllocatedNum::order_siblings(cs.namespace(|| "order"), &current, &sibling, &bit)
+                    .unwrap();
+            assert!(cs.is_satisfied());
+
+            let (const_left, const_right) = AllocatedNum::order_siblings(
+                cs.namespace(|| "order constant"),
+                &current,
+                &sibling,
+                &Boolean::constant(is_right),
+            )
+            .unwrap();
+
+            let expected = if is_right {
+                (Fr::from(2u64), Fr::from(1u64))
+            } else {
+                (Fr::from(1u64), Fr::from(2u64))
+            };
+            assert_eq!(
+                (left.get_value(), right.get_value()),
+                (Some(expected.0), Some(expected.1))
+            );
+            assert_eq!(
+                (const_left.get_value(), const_right.get_value()),
+                (Some(expected.0), Some(expected.1))
+            );
+        }
+    }
+
+    #[test]
+    fn test_num_partition() {
+        use super::AllocatedBit;
+
+        for condition in [false, true] {
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from(1u64))).unwrap();
+            let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::from(2u64))).unwrap();
+            let bit = Boolean::from(
+                AllocatedBit::alloc(cs.namespace(|| "condition"), Some(condition)).unwrap(),
+            );
+
+            let (selected, other) =
+                AllocatedNum::partition(cs.namespace(|| "partition"), &a, &b, &bit).unwrap();
+            let select =
+                AllocatedNum::conditionally_select(cs.namespace(|| "select"), &a, &b, &bit)
+                    .unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(selected.get_value(), select.get_value());
+            let (expected, rest) = if condition { (&b, &a) } else { (&a, &b) };
+            assert_eq!(selected.get_value(), expected.get_value());
+            assert_eq!(other.get_value(), rest.get_value());
+        }
+    }
+
+    #[test]
+    fn test_num_select4() {
+        use super::AllocatedBit;
+
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let values = [(); 4].map(|_| Fr::random(&mut rng));
+
+        for (c1, c0) in [(false, false), (false, true), (true, false), (true, true)] {
+            let expected = values[usize::from(c1) * 2 + usize::from(c0)];
+
+            for constant in [false, true] {
+                let mut cs = TestConstraintSystem::<Fr>::new();
+                let inputs = [0, 1, 2, 3].map(|i| {
+                    AllocatedNum::alloc(cs.namespace(|| format!("a{i}")), || Ok(values[i])).unwrap()
+                });
+                let bit = |cs: &mut TestConstraintSystem<Fr>, name: &str, value| {
+                    if constant {
+                        Boolean::constant(value)
+                    } else {
+                        AllocatedBit::alloc(cs.namespace(|| name), Some(value))
+                            .unwrap()
+                            .into()
+                    }
+                };
+                let b1 = bit(&mut cs, "b1", c1);
+                let b0 = bit(&mut cs, "b0", c0);
+
+                let before = cs.num_constraints();
+                let out =
+                    AllocatedNum::select4(cs.namespace(|| "select4"), &inputs, &b1, &b0).unwrap();
+                let select4_constraints = cs.num_constraints() - before;
+
+                let before = cs.num_constraints();
+                let mux = AllocatedNum::mux_tree(
+                    &mut cs.namespace(|| "mux tree"),
+                    [b1, b0].iter(),
+                    &inputs,
+                )
+                .unwrap();
+                let mux_constraints = cs.num_constraints() - before;
+
+                assert!(cs.is_satisfied());
+                assert_eq!(out.get_value().unwrap(), expected);
+                assert_eq!(mux.get_value().unwrap(), expected);
+                assert!(select4_constraints <= mux_constraints);
+                if constant {
+                    assert_eq!(select4_constraints, 0);
+                } else {
+                    assert_eq!(select4_constraints, 3);
+                    cs.set("select4/out/num", expected + Fr::ONE);
+                    assert_eq!(cs.which_is_unsatisfied(), Some("select4/out constraint"));
+                }
+            }
+
+            // With a constant high bit, select4 needs a single selection
+            // where the mux tree still selects in both halves.
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let inputs = [0, 1, 2, 3].map(|i| {
+                AllocatedNum::alloc(cs.namespace(|| format!("a{i}")), || Ok(values[i])).unwrap()
+            });
+            let b1 = Boolean::constant(c1);
+            let b0 = Boolean::from(AllocatedBit::alloc(cs.namespace(|| "b0"), Some(c0)).unwrap());
+
+            let before = cs.num_constraints();
+            let out = AllocatedNum::select4(cs.namespace(|| "select4"), &inputs, &b1, &b0).unwrap();
+            let select4_constraints = cs.num_constraints() - before;
+
+            let before = cs.num_constraints();
+            AllocatedNum::mux_tree(&mut cs.namespace(|| "mux tree"), [b1, b0].iter(), &inputs)
+                .unwrap();
+            let mux_constraints = cs.num_constraints() - before;
+
+            assert!(cs.is_satisfied());
+            assert_eq!(out.get_value().unwrap(), expected);
+            assert_eq!(select4_constraints, 1);
+            assert!(select4_constraints < mux_constraints);
+        }
+    }
+
+    #[test]
+    fn test_mux_tree() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let conditions = vec![(false, false), (false, true), (true, false), (true, true)];
+
+        for (c1, c0) in conditions {
+            let mut cs = TestConstraintSystem::<Fr>::new();
+
+            let condition0 = Boolean::constant(c0);
+            let condition1 = Boolean::constant(c1);
+            let select = &[condition1, condition0];
+
+            let a0 = AllocatedNum::alloc(cs.namespace(|| "alloc a0"), || Ok(Fr::random(&mut rng)))
+                .unwrap();
+            let a1 = AllocatedNum::alloc(cs.namespace(|| "alloc a1"), || Ok(Fr::random(&mut rng)))
+                .unwrap();
+            let a2 = AllocatedNum::alloc(cs.namespace(|| "alloc a2"), || Ok(Fr::random(&mut rng)))
+                .unwrap();
+            let a3 = AllocatedNum::alloc(cs.namespace(|| "alloc a3"), || Ok(Fr::random(&mut rng)))
+                .unwrap();
+
+            let res = AllocatedNum::<Fr>::mux_tree(
+                &mut cs.namespace(|| format!("mux tree result for conditions = {c1}, {c0}")),
+                select.iter(),
+                &[a0.clone(), a1.clone(), a2.clone(), a3.clone()],
+            );
+            assert!(res.is_ok());
+            let res = res.unwrap();
+
+            let res_expected = match (c1, c0) {
+                (false, false) => a0.clone(),
+                (false, true) => a1.clone(),
+                (true, false) => a2.clone(),
+                (true, true) => a3.clone(),
+            };
+            cs.enforce(
+                || format!("res equality for conditions = {c1}, {c0}"),
+                |lc| lc,
+                |lc| lc,
+                |lc| lc + res_expected.get_variable() - res.get_variable(),
+            );
+
+            assert!(cs.is_satisfied());
+            // Constant selectors fold away, leaving only the equality check.
+            assert_eq!(cs.num_constraints(), 1);
+        }
+    }
+
+    #[test]
+    fn test_num_nonzero() {
+        {
+            let mut cs = TestConstraintSystem::<Fr>::new();
+
+            let n = AllocatedNum::alloc(&mut cs, || Ok(Fr::from(3u64))).unwrap();
+            n.assert_nonzero(&mut cs).unwrap();
+
+            assert!(cs.is_satisfied());
+            cs.set("ephemeral inverse", Fr::from(3u64));
+            assert!(cs.which_is_unsatisfied() == Some("nonzero assertion constraint"));
+        }
+        {
+            let mut cs = TestConstraintSystem::<Fr>::new();
+
+            let n = AllocatedNum::alloc(&mut cs, || Ok(Fr::ZERO)).unwrap();
+            assert!(n.assert_nonzero(&mut cs).is_err());
+        }
+    }
+
+    #[test]
+    fn test_num_enforce_nonzero() {
+        {
+            let mut cs = TestConstraintSystem::<Fr>::new();
+
+            let n = AllocatedNum::alloc(&mut cs, || Ok(Fr::from(3u64))).unwrap();
+            let inv = n.enforce_nonzero(&mut cs).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(cs.num_constraints(), 1);
+            assert_eq!(inv.get_value().unwrap(), Fr::from(3u64).invert().unwrap());
+            assert_eq!(cs.get("ephemeral inverse"), inv.get_value().unwrap());
+            cs.set("ephemeral inverse", Fr::from(3u64));
+            assert!(cs.which_is_unsatisfied() == Some("nonzero assertion constraint"));
+        }
+        {
+            let mut cs = TestConstraintSystem::<Fr>::new();
+
+            let n = AllocatedNum::alloc(&mut cs, || Ok(Fr::ZERO)).unwrap();
+            assert!(matches!(
+                n.enforce_nonzero(&mut cs),
+                Err(SynthesisError::DivisionByZero)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_num_sqrt_or_zero() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let g = Fr::MULTIPLICATIVE_GENERATOR;
+
+        for i in 0..20 {
+            let r = Fr::random(&mut rng);
+            let is_residue = i % 2 == 0;
+            let value = if is_residue {
+                r.square()
+            } else {
+                r.square() * g
+            };
+
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let n = AllocatedNum::alloc(cs.namespace(|| "n"), || Ok(value)).unwrap();
+            let (root, flag) = n.sqrt_or_zero(cs.namespace(|| "sqrt")).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(flag.get_value(), Some(is_residue));
+            if is_residue {
+                assert_eq!(root.get_value().unwrap().square(), value);
+            } else {
+                assert_eq!(root.get_value(), Some(Fr::ZERO));
+            }
+
+            // Flipping the flag cannot be satisfied by the other branch.
+            cs.set("sqrt/is residue/boolean", Fr::from(u64::from(!is_residue)));
+            cs.set(
+                "sqrt/non-residue term/num",
+                if is_residue { value } else { Fr::ZERO },
+            );
+            assert_eq!(cs.which_is_unsatisfied(), Some("sqrt/residue constraint"));
+        }
 
-    pub fn get_value(&self) -> Option<Scalar> {
-        self.value
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let n = AllocatedNum::alloc(cs.namespace(|| "n"), || Ok(Fr::ZERO)).unwrap();
+        let (root, flag) = n.sqrt_or_zero(cs.namespace(|| "sqrt")).unwrap();
+        assert!(cs.is_satisfied());
+        assert_eq!(flag.get_value(), Some(true));
+        assert_eq!(root.get_value(), Some(Fr::ZERO));
     }
 
-    pub fn lc(&self, coeff: Scalar) -> LinearCombination<Scalar> {
-        LinearCombination::zero() + (coeff, &self.lc)
-    }
+    #[test]
+    fn test_num_product_is_one() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        {
+            let mut cs = TestConstraintSystem::<Fr>::new();
 
-    pub fn add_bool_with_coeff(self, one: Variable, bit: &Boolean, coeff: Scalar) -> Self {
-        let newval = match (self.value, bit.get_value()) {
-            (Some(mut curval), Some(bval)) => {
-                if bval {
-                    curval.add_assign(&coeff);
-                }
+            let r = Fr::random(&mut rng);
+            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(r)).unwrap();
+            let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(r.invert().unwrap())).unwrap();
+            AllocatedNum::enforce_product_is_one(&mut cs, &a, &b).unwrap();
 
-                Some(curval)
-            }
-            _ => None,
-        };
+            assert!(cs.is_satisfied());
+            assert_eq!(cs.num_constraints(), 1);
+        }
+        {
+            let mut cs = TestConstraintSystem::<Fr>::new();
 
-        Num {
-            value: newval,
-            lc: self.lc + &bit.lc(one, coeff),
+            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from(2u64))).unwrap();
+            let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::from(2u64))).unwrap();
+            AllocatedNum::enforce_product_is_one(&mut cs, &a, &b).unwrap();
+
+            assert_eq!(cs.which_is_unsatisfied(), Some("product is one constraint"));
         }
     }
 
-    #[allow(clippy::should_implement_trait)]
-    pub fn add(self, other: &Self) -> Self {
-        let lc = self.lc + &other.lc;
-        let value = match (self.value, other.value) {
-            (Some(v1), Some(v2)) => {
-                let mut tmp = v1;
-                tmp.add_assign(&v2);
-                Some(tmp)
-            }
-            _ => None,
-        };
+    #[test]
+    fn test_num_units_product() {
+        // (a, b, expected, satisfied)
+        let cases = [
+            (3u64, 5u64, 15u64, true),
+            (7, 1, 7, true),
+            (0, 9, 0, true),
+            (3, 5, 16, false),
+            (0, 9, 1, false),
+        ];
+
+        for (a_val, b_val, expected, satisfied) in cases {
+            let mut cs = TestConstraintSystem::<Fr>::new();
 
-        Num { value, lc }
-    }
+            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from(a_val))).unwrap();
+            let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::from(b_val))).unwrap();
+            AllocatedNum::enforce_units_product(&mut cs, &a, &b, Fr::from(expected)).unwrap();
 
-    pub fn scale(mut self, scalar: Scalar) -> Self {
-        for (_variable, fr) in self.lc.iter_mut() {
-            fr.mul_assign(&scalar);
+            assert_eq!(cs.is_satisfied(), satisfied);
+            assert_eq!(cs.num_constraints(), 1);
         }
+    }
 
-        if let Some(ref mut v) = self.value {
-            v.mul_assign(&scalar);
-        }
+    #[test]
+    fn test_into_bits_strict() {
+        let negone = -Fr::ONE;
 
-        self
-    }
-}
+        let mut cs = TestConstraintSystem::<Fr>::new();
 
-#[cfg(test)]
-mod test {
-    use std::ops::{AddAssign, MulAssign, Neg, SubAssign};
+        let n = AllocatedNum::alloc(&mut cs, || Ok(negone)).unwrap();
+        n.to_bits_le_strict(&mut cs).unwrap();
 
-    use crate::ConstraintSystem;
-    use blstrs::Scalar as Fr;
-    use ff::{Field, PrimeField, PrimeFieldBits};
-    use rand_core::SeedableRng;
-    use rand_xorshift::XorShiftRng;
+        assert!(cs.is_satisfied());
 
-    use super::{AllocatedNum, Boolean, Num};
-    use crate::util_cs::test_cs::*;
+        // make the bit representation the characteristic
+        cs.set("bit 254/boolean", Fr::ONE);
+
+        // this makes the conditional boolean constraint fail
+        assert_eq!(
+            cs.which_is_unsatisfied().unwrap(),
+            "bit 254/boolean constraint"
+        );
+    }
 
     #[test]
-    fn test_allocated_num() {
+    fn test_num_from_lc() {
         let mut cs = TestConstraintSystem::<Fr>::new();
+        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from(3u64))).unwrap();
+        let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::from(5u64))).unwrap();
 
-        AllocatedNum::alloc(&mut cs, || Ok(Fr::ONE)).unwrap();
+        // 2a - b + 7
+        let lc = LinearCombination::zero() + (Fr::from(2u64), a.get_variable()) - b.get_variable()
+            + (Fr::from(7u64), TestConstraintSystem::<Fr>::one());
+        let c = AllocatedNum::from_lc(cs.namespace(|| "c"), lc, Some(Fr::from(8u64))).unwrap();
 
-        assert!(cs.get("num") == Fr::ONE);
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), 1);
+        assert_eq!(c.get_value().unwrap(), Fr::from(8u64));
+        assert_eq!(cs.get("c/num"), Fr::from(8u64));
+
+        cs.set("c/num", Fr::from(9u64));
+        assert_eq!(
+            cs.which_is_unsatisfied(),
+            Some("c/linear combination constraint")
+        );
     }
 
     #[test]
-    fn test_allocated_infallible_num() {
+    fn test_inputize_vec() {
         let mut cs = TestConstraintSystem::<Fr>::new();
+        let nums = (0..3u64)
+            .map(|i| {
+                AllocatedNum::alloc(cs.namespace(|| format!("a {}", i)), || Ok(Fr::from(i + 1)))
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
 
-        AllocatedNum::alloc_infallible(&mut cs, || Fr::ONE);
+        AllocatedNum::inputize_vec(cs.namespace(|| "outputs"), &nums).unwrap();
 
-        assert!(cs.get("num") == Fr::ONE);
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_inputs(), nums.len() + 1);
+        assert_eq!(cs.num_constraints(), nums.len());
+        assert!(cs.verify(&[Fr::from(1u64), Fr::from(2u64), Fr::from(3u64)]));
+
+        cs.set("outputs/input 1/input variable", Fr::from(5u64));
+        assert_eq!(
+            cs.which_is_unsatisfied(),
+            Some("outputs/input 1/enforce input is correct")
+        );
     }
 
     #[test]
-    fn test_num_partial_addition() {
-        let a = Num::<Fr>::zero();
-        let b = Num::<Fr> {
-            value: None,
-            lc: Default::default(),
-        };
-        let c = a.clone().add(&b);
-        assert!(c.value.is_none());
-        let c = b.clone().add(&a);
-        assert!(c.value.is_none());
-        let c = b.clone().add(&b);
-        assert!(c.value.is_none());
-        let c = a.clone().add(&a);
-        assert!(c.value == Some(Fr::ZERO));
+    fn test_pack_bits_as_input() {
+        use super::AllocatedBit;
+
+        let value = 0b1011_0010u64;
+
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let mut bits = (0..8)
+            .map(|i| {
+                Boolean::from(
+                    AllocatedBit::alloc(
+                        cs.namespace(|| format!("bit {i}")),
+                        Some((value >> i) & 1 == 1),
+                    )
+                    .unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+        bits.push(Boolean::constant(true));
+
+        let input = AllocatedNum::pack_bits_as_input(cs.namespace(|| "packed"), &bits).unwrap();
+        let expected = Fr::from(value + (1 << 8));
+
+        assert!(cs.is_satisfied());
+        assert_eq!(input.get_value(), Some(expected));
+        assert!(cs.verify(&[expected]));
+
+        cs.set("packed/input/input num", expected + Fr::ONE);
+        assert_eq!(cs.which_is_unsatisfied(), Some("packed/packing constraint"));
     }
 
     #[test]
-    fn test_num_addition() {
-        let mut cs = TestConstraintSystem::<Fr>::new();
+    fn test_num_parity() {
+        for value in [0u64, 1, 2, 7, 100, 255] {
+            let even = value % 2 == 0;
 
-        let mut char = Fr::char();
-        char[0] -= 1u8;
-        let mod_minus_one = Fr::from_repr(char);
-        assert!(bool::from(mod_minus_one.is_some()));
-        let mod_minus_one = mod_minus_one.unwrap();
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from(value))).unwrap();
+            let is_even = a.is_even(cs.namespace(|| "is even"), 8).unwrap();
+            assert!(cs.is_satisfied());
+            assert_eq!(is_even.get_value().unwrap(), even);
 
-        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(mod_minus_one)).unwrap();
-        let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::ONE)).unwrap();
-        let c = a.add(&mut cs, &b).unwrap();
+            a.enforce_even(cs.namespace(|| "enforce even"), 8).unwrap();
+            assert_eq!(cs.is_satisfied(), even);
 
-        assert!(cs.is_satisfied());
-        assert!(cs.get("sum num") == Fr::ZERO);
-        assert!(c.value.unwrap() == Fr::ZERO);
-        cs.set("sum num", Fr::ONE);
-        assert!(!cs.is_satisfied());
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from(value))).unwrap();
+            a.enforce_odd(cs.namespace(|| "enforce odd"), 8).unwrap();
+            if even {
+                assert_eq!(
+                    cs.which_is_unsatisfied(),
+                    Some("enforce odd/odd/enforce equal to zero")
+                );
+            } else {
+                assert!(cs.is_satisfied());
+            }
+        }
     }
 
     #[test]
-    fn test_num_subraction() {
-        let mut cs = TestConstraintSystem::<Fr>::new();
+    fn test_num_sign_bit() {
+        for (value, negative) in [
+            (0u64, false),
+            (127, false),
+            (128, true),
+            (129, true),
+            (255, true),
+        ] {
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from(value))).unwrap();
+            let sign = a.sign_bit(cs.namespace(|| "sign"), 8).unwrap();
 
-        let mut char = Fr::char();
-        char[0] -= 1u8;
-        let mod_minus_one = Fr::from_repr(char);
-        assert!(bool::from(mod_minus_one.is_some()));
-        let mod_minus_one = mod_minus_one.unwrap();
+            assert!(cs.is_satisfied());
+            assert_eq!(sign.get_value().unwrap(), negative);
 
-        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::ZERO)).unwrap();
-        let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::ONE)).unwrap();
-        let c = a.sub(&mut cs, &b).unwrap();
+            cs.set(
+                "sign/bit 7/boolean",
+                if negative { Fr::ZERO } else { Fr::ONE },
+            );
+            assert_eq!(cs.which_is_unsatisfied(), Some("sign/packing constraint"));
+        }
 
-        assert!(cs.is_satisfied());
-        assert!(cs.get("sub num") == mod_minus_one);
-        assert!(c.value.unwrap() == mod_minus_one);
-        cs.set("sub num", Fr::ONE);
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from(256u64))).unwrap();
+        a.sign_bit(cs.namespace(|| "sign"), 8).unwrap();
         assert!(!cs.is_satisfied());
     }
 
     #[test]
-    fn test_num_negation() {
+    fn test_to_bits_le_many() {
         let mut rng = XorShiftRng::from_seed([
             0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
             0xbc, 0xe5,
         ]);
+
         let mut cs = TestConstraintSystem::<Fr>::new();
+        let nums = (0..3)
+            .map(|i| {
+                AllocatedNum::alloc(cs.namespace(|| format!("a {}", i)), || {
+                    Ok(Fr::random(&mut rng))
+                })
+                .unwrap()
+            })
+            .collect::<Vec<_>>();
 
-        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::random(&mut rng))).unwrap();
-        let out = a.neg(&mut cs).unwrap();
-        let out_exp = a.get_value().unwrap().neg();
+        let bits = AllocatedNum::to_bits_le_many(cs.namespace(|| "many"), &nums).unwrap();
 
         assert!(cs.is_satisfied());
-        assert!(cs.get("neg num") == out_exp);
-        assert!(out.value.unwrap() == out_exp);
-        cs.set("neg num", Fr::random(&mut rng));
-        assert!(!cs.is_satisfied());
-    }
-
-    #[test]
-    fn test_num_squaring() {
-        let mut cs = TestConstraintSystem::<Fr>::new();
+        assert_eq!(bits.len(), nums.len());
+        assert_eq!(
+            cs.num_constraints(),
+            nums.len() * (Fr::NUM_BITS as usize + 1)
+        );
 
-        let n = AllocatedNum::alloc(&mut cs, || Ok(Fr::from(3u64))).unwrap();
-        let n2 = n.square(&mut cs).unwrap();
+        for (i, (num, bits)) in nums.iter().zip(&bits).enumerate() {
+            let expected = num
+                .to_bits_le(cs.namespace(|| format!("single {}", i)))
+                .unwrap();
+            assert_eq!(
+                bits.iter().map(|b| b.get_value()).collect::<Vec<_>>(),
+                expected.iter().map(|b| b.get_value()).collect::<Vec<_>>()
+            );
+        }
 
-        assert!(cs.is_satisfied());
-        assert!(cs.get("squared num") == Fr::from(9u64));
-        assert!(n2.value.unwrap() == Fr::from(9u64));
-        cs.set("squared num", Fr::from(10u64));
-        assert!(!cs.is_satisfied());
+        let bit = cs.get("many/num 1/bit 0/boolean");
+        cs.set("many/num 1/bit 0/boolean", Fr::ONE - bit);
+        assert_eq!(
+            cs.which_is_unsatisfied(),
+            Some("many/num 1/unpacking constraint")
+        );
     }
 
     #[test]
-    fn test_num_multiplication() {
-        let mut cs = TestConstraintSystem::<Fr>::new();
+    fn test_into_bits() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
 
-        let n = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from(12u64))).unwrap();
-        let n2 = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::from(10u64))).unwrap();
-        let n3 = n.mul(&mut cs, &n2).unwrap();
+        for i in 0..200 {
+            let r = Fr::random(&mut rng);
+            let mut cs = TestConstraintSystem::<Fr>::new();
 
-        assert!(cs.is_satisfied());
-        assert!(cs.get("product num") == Fr::from(120u64));
-        assert!(n3.value.unwrap() == Fr::from(120u64));
-        cs.set("product num", Fr::from(121u64));
-        assert!(!cs.is_satisfied());
-    }
+            let n = AllocatedNum::alloc(&mut cs, || Ok(r)).unwrap();
 
-    #[test]
-    fn test_num_division() {
-        let mut cs = TestConstraintSystem::<Fr>::new();
+            let bits = if i % 2 == 0 {
+                n.to_bits_le(&mut cs).unwrap()
+            } else {
+                n.to_bits_le_strict(&mut cs).unwrap()
+            };
 
-        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from(120u64))).unwrap();
-        let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::from(10u64))).unwrap();
-        let c = a.div(&mut cs, &b).unwrap();
+            assert!(cs.is_satisfied());
 
-        assert!(cs.is_satisfied());
-        assert!(cs.get("div num") == Fr::from(12u64));
-        assert!(c.value.unwrap() == Fr::from(12u64));
-        cs.set("div num", Fr::from(11u64));
-        assert!(!cs.is_satisfied());
+            for (i, b) in r.to_le_bits().iter().enumerate() {
+                // `r.to_le_bits()` contains every bit in a representation (including bits which
+                // exceed the field size), whereas the length of `bits` does not exceed the field
+                // size.
+                match bits.get(i) {
+                    Some(Boolean::Is(a)) => assert_eq!(b, a.get_value().unwrap()),
+                    Some(_) => unreachable!(),
+                    None => assert!(!b),
+                };
+            }
+
+            cs.set("num", Fr::random(&mut rng));
+            assert!(!cs.is_satisfied());
+            cs.set("num", r);
+            assert!(cs.is_satisfied());
+
+            for i in 0..Fr::NUM_BITS {
+                let name = format!("bit {}/boolean", i);
+                let cur = cs.get(&name);
+                let mut tmp = Fr::ONE;
+                tmp.sub_assign(&cur);
+                cs.set(&name, tmp);
+                assert!(!cs.is_satisfied());
+                cs.set(&name, cur);
+                assert!(cs.is_satisfied());
+            }
+        }
     }
 
     #[test]
-    fn test_num_is_zero() {
+    fn test_to_windows() {
         let mut rng = XorShiftRng::from_seed([
             0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
             0xbc, 0xe5,
         ]);
-        {
+
+        for window_bits in [1, 3, 4, 8] {
+            let r = Fr::random(&mut rng);
             let mut cs = TestConstraintSystem::<Fr>::new();
-            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::random(&mut rng))).unwrap();
-            let is_zero = a.is_zero(&mut cs).unwrap();
+
+            let n = AllocatedNum::alloc(&mut cs, || Ok(r)).unwrap();
+            let windows = n.to_windows(&mut cs, window_bits).unwrap();
 
             assert!(cs.is_satisfied());
-            assert!(cs.get("out bit/boolean") == Fr::from(0u64));
-            assert!(!is_zero.get_value().unwrap());
-            cs.set("out bit/boolean", Fr::from(1u64));
-            assert!(!cs.is_satisfied());
+            assert_eq!(
+                windows.len(),
+                (Fr::NUM_BITS as usize + window_bits - 1) / window_bits
+            );
+
+            // Reconstruct the value from the windows.
+            let mut acc = Fr::ZERO;
+            let mut coeff = Fr::ONE;
+            for window in &windows {
+                assert_eq!(window.len(), window_bits);
+                for bit in window {
+                    if bit.get_value().unwrap() {
+                        acc.add_assign(&coeff);
+                    }
+                    coeff = coeff.double();
+                }
+            }
+            assert_eq!(acc, r);
         }
+    }
+
+    #[test]
+    fn test_boolean_implication_chain() {
+        use super::AllocatedBit;
+
+        let alloc_chain = |cs: &mut TestConstraintSystem<Fr>, values: &[bool]| {
+            values
+                .iter()
+                .enumerate()
+                .map(|(i, v)| {
+                    Boolean::from(
+                        AllocatedBit::alloc(cs.namespace(|| format!("b{}", i)), Some(*v)).unwrap(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
 
         {
             let mut cs = TestConstraintSystem::<Fr>::new();
-            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::ZERO)).unwrap();
-            let is_zero = a.is_zero(&mut cs).unwrap();
+            let bits = alloc_chain(&mut cs, &[false, false, true, true]);
+            AllocatedNum::enforce_boolean_implication_chain(&mut cs, &bits).unwrap();
+
             assert!(cs.is_satisfied());
-            assert!(cs.get("out bit/boolean") == Fr::from(1u64));
-            assert!(is_zero.get_value().unwrap());
-            cs.set("out bit/boolean", Fr::from(0u64));
-            assert!(!cs.is_satisfied());
+            assert_eq!(cs.num_constraints(), 4 + 3);
         }
-    }
 
-    #[test]
-    fn test_num_is_equal() {
-        let mut rng = XorShiftRng::from_seed([
-            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
-            0xbc, 0xe5,
-        ]);
         {
             let mut cs = TestConstraintSystem::<Fr>::new();
-            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::random(&mut rng))).unwrap();
-            let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::random(&mut rng))).unwrap();
-            let is_equal = a.is_equal(&mut cs, &b).unwrap();
+            let bits = alloc_chain(&mut cs, &[false, true, false, true]);
+            AllocatedNum::enforce_boolean_implication_chain(&mut cs, &bits).unwrap();
 
-            assert!(cs.is_satisfied());
-            assert!(cs.get("out bit/boolean") == Fr::from(0u64));
-            assert!(!is_equal.get_value().unwrap());
-            cs.set("out bit/boolean", Fr::from(1u64));
-            assert!(!cs.is_satisfied());
+            assert_eq!(cs.which_is_unsatisfied(), Some("bit 1 implies bit 2"));
         }
 
         {
             let mut cs = TestConstraintSystem::<Fr>::new();
-            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::random(&mut rng))).unwrap();
-            let b = a.clone();
-            let is_equal = a.is_equal(&mut cs, &b).unwrap();
+            let bits = [Boolean::constant(true), Boolean::constant(false)];
+            assert!(AllocatedNum::enforce_boolean_implication_chain(&mut cs, &bits).is_err());
+        }
+    }
 
-            assert!(cs.is_satisfied());
-            assert!(cs.get("out bit/boolean") == Fr::from(1u64));
-            assert!(is_equal.get_value().unwrap());
-            cs.set("out bit/boolean", Fr::from(0u64));
-            assert!(!cs.is_satisfied());
+    #[test]
+    fn test_enforce_permutation() {
+        use super::AllocatedBit;
+
+        fn synthesize(
+            input_values: &[u64],
+            output_values: &[u64],
+            matrix: &[bool],
+        ) -> TestConstraintSystem<Fr> {
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let alloc_nums = |cs: &mut TestConstraintSystem<Fr>, name: &str, values: &[u64]| {
+                values
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| {
+                        AllocatedNum::alloc(cs.namespace(|| format!("{} {}", name, i)), || {
+                            Ok(Fr::from(*v))
+                        })
+                        .unwrap()
+                    })
+                    .collect::<Vec<_>>()
+            };
+            let input = alloc_nums(&mut cs, "input", input_values);
+            let output = alloc_nums(&mut cs, "output", output_values);
+            let perm = matrix
+                .iter()
+                .enumerate()
+                .map(|(i, b)| {
+                    Boolean::from(
+                        AllocatedBit::alloc(cs.namespace(|| format!("perm {}", i)), Some(*b))
+                            .unwrap(),
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            AllocatedNum::enforce_permutation(&mut cs, &input, &output, &perm).unwrap();
+            cs
         }
+
+        // output[0] = input[2], output[1] = input[0], output[2] = input[1]
+        let matrix = [false, false, true, true, false, false, false, true, false];
+        let cs = synthesize(&[10, 20, 30], &[30, 10, 20], &matrix);
+        assert!(cs.is_satisfied());
+
+        // Correct values but a matrix which is not a permutation.
+        let matrix = [false, false, true, true, false, false, true, false, false];
+        let cs = synthesize(&[10, 20, 30], &[30, 10, 10], &matrix);
+        assert_eq!(cs.which_is_unsatisfied(), Some("column 0 sums to one"));
+
+        // A valid permutation matrix which does not match the outputs.
+        let matrix = [false, false, true, true, false, false, false, true, false];
+        let cs = synthesize(&[10, 20, 30], &[30, 20, 10], &matrix);
+        assert!(!cs.is_satisfied());
+
+        // Mismatched lengths are rejected.
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::ONE)).unwrap();
+        assert!(
+            AllocatedNum::enforce_permutation(&mut cs, &[a], &[], &[Boolean::constant(true)])
+                .is_err()
+        );
     }
 
     #[test]
-    fn test_num_conditional_select() {
+    fn test_eval_poly() {
         let mut rng = XorShiftRng::from_seed([
             0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
             0xbc, 0xe5,
         ]);
-        {
+
+        for degree in 0..5 {
             let mut cs = TestConstraintSystem::<Fr>::new();
 
-            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::random(&mut rng))).unwrap();
-            let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::random(&mut rng))).unwrap();
-            let condition = Boolean::constant(false);
-            let c = AllocatedNum::conditionally_select(&mut cs, &a, &b, &condition).unwrap();
+            let coeffs = (0..=degree)
+                .map(|i| {
+                    AllocatedNum::alloc(cs.namespace(|| format!("c{}", i)), || {
+                        Ok(Fr::random(&mut rng))
+                    })
+                    .unwrap()
+                })
+                .collect::<Vec<_>>();
+            let x = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(Fr::random(&mut rng))).unwrap();
+
+            let y = AllocatedNum::eval_poly(cs.namespace(|| "eval"), &coeffs, &x).unwrap();
+
+            let x_value = x.get_value().unwrap();
+            let mut expected = Fr::ZERO;
+            let mut power = Fr::ONE;
+            for c in &coeffs {
+                expected.add_assign(c.get_value().unwrap() * power);
+                power.mul_assign(&x_value);
+            }
 
             assert!(cs.is_satisfied());
-            assert_eq!(a.value.unwrap(), c.value.unwrap());
+            assert_eq!(y.get_value().unwrap(), expected);
+            assert_eq!(cs.num_constraints(), degree);
         }
 
         {
             let mut cs = TestConstraintSystem::<Fr>::new();
-
-            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::random(&mut rng))).unwrap();
-            let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::random(&mut rng))).unwrap();
-            let condition = Boolean::constant(true);
-            let c = AllocatedNum::conditionally_select(&mut cs, &a, &b, &condition).unwrap();
+            let x = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(Fr::ONE)).unwrap();
+            let y = AllocatedNum::eval_poly(cs.namespace(|| "eval"), &[], &x).unwrap();
 
             assert!(cs.is_satisfied());
-            assert_eq!(b.value.unwrap(), c.value.unwrap());
+            assert_eq!(y.get_value().unwrap(), Fr::ZERO);
+            cs.set("eval/zero/alloc constant/num", Fr::ONE);
+            assert!(!cs.is_satisfied());
         }
     }
 
     #[test]
-    fn test_num_conditional_reversal() {
-        let mut rng = XorShiftRng::from_seed([
-            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
-            0xbc, 0xe5,
-        ]);
-        {
-            let mut cs = TestConstraintSystem::<Fr>::new();
+    fn test_allocated_num_hex_roundtrip() {
+        let mut cs = TestConstraintSystem::<Fr>::new();
 
-            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::random(&mut rng))).unwrap();
-            let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::random(&mut rng))).unwrap();
-            let condition = Boolean::constant(false);
-            let (c, d) = AllocatedNum::conditionally_reverse(&mut cs, &a, &b, &condition).unwrap();
+        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from(42u64))).unwrap();
+        let b = AllocatedNum::alloc_input(cs.namespace(|| "b"), || Ok(-Fr::ONE)).unwrap();
 
-            assert!(cs.is_satisfied());
+        let json = serde_json::to_string(&AllocatedNumHex(a)).unwrap();
+        assert_eq!(
+            json,
+            format!(
+                r#"{{"value":"0x{}2a","variable":{{"Aux":0}}}}"#,
+                "0".repeat(62)
+            )
+        );
 
-            assert_eq!(a.value.unwrap(), c.value.unwrap());
-            assert_eq!(b.value.unwrap(), d.value.unwrap());
+        for num in [a, b] {
+            let json = serde_json::to_string(&AllocatedNumHex(num)).unwrap();
+            let AllocatedNumHex(decoded) =
+                serde_json::from_str::<AllocatedNumHex<Fr>>(&json).unwrap();
+            assert_eq!(decoded.get_value(), num.get_value());
+            assert_eq!(decoded.get_variable(), num.get_variable());
         }
 
-        {
-            let mut cs = TestConstraintSystem::<Fr>::new();
+        // The modulus itself is not a canonical field element.
+        let modulus = "0x73eda753299d7d483339d80809a1d80553bda402fffe5bfeffffffff00000001";
+        let json = format!(r#"{{"value":"{}","variable":{{"Aux":0}}}}"#, modulus);
+        assert!(serde_json::from_str::<AllocatedNumHex<Fr>>(&json).is_err());
 
-            let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::random(&mut rng))).unwrap();
-            let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::random(&mut rng))).unwrap();
-            let condition = Boolean::constant(true);
-            let (c, d) = AllocatedNum::conditionally_reverse(&mut cs, &a, &b, &condition).unwrap();
+        let json = r#"{"value":null,"variable":{"Input":1}}"#;
+        let AllocatedNumHex(decoded) = serde_json::from_str::<AllocatedNumHex<Fr>>(json).unwrap();
+        assert_eq!(decoded.get_value(), None);
+        assert_eq!(decoded.get_variable(), b.get_variable());
+    }
 
-            assert!(cs.is_satisfied());
+    #[test]
+    fn test_witness_only_roundtrip() {
+        let mut cs = TestConstraintSystem::<Fr>::new();
 
-            assert_eq!(a.value.unwrap(), d.value.unwrap());
-            assert_eq!(b.value.unwrap(), c.value.unwrap());
-        }
+        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from(42u64))).unwrap();
+        let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::from(7u64))).unwrap();
+
+        // Only the value is persisted, so numbers at different variables
+        // with the same value serialize identically.
+        let bytes = bincode::serialize(&b.witness_only()).unwrap();
+        assert_eq!(bytes, bincode::serialize(&Some(Fr::from(7u64))).unwrap());
+        assert_ne!(bytes, bincode::serialize(&b).unwrap());
+
+        let cached: WitnessOnly<Fr> = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(cached.get_value(), Some(Fr::from(7u64)));
+
+        // Replay the cached witness into a fresh constraint system.
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let c = cached.alloc(cs.namespace(|| "c")).unwrap();
+        assert_eq!(c.get_value(), b.get_value());
+        assert_eq!(c.get_variable(), a.get_variable());
+        assert!(cs.get("c/num") == Fr::from(7u64));
+
+        let missing = WitnessOnly::<Fr>(None);
+        assert!(missing.alloc(cs.namespace(|| "d")).is_err());
     }
 
     #[test]
-    fn test_mux_tree() {
+    fn test_value_or_missing() {
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from(3u64))).unwrap();
+        assert_eq!(a.value_or_missing().unwrap(), Fr::from(3u64));
+
+        let num = Num::from(a);
+        assert_eq!(num.value_or_missing().unwrap(), Fr::from(3u64));
+
+        let missing = Num::<Fr> {
+            value: None,
+            lc: LinearCombination::zero(),
+        };
+        assert!(matches!(
+            missing.value_or_missing(),
+            Err(SynthesisError::AssignmentMissing)
+        ));
+    }
+
+    #[test]
+    fn test_num_conditional_scan() {
+        use super::AllocatedBit;
+
         let mut rng = XorShiftRng::from_seed([
             0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
             0xbc, 0xe5,
         ]);
 
-        let conditions = vec![(false, false), (false, true), (true, false), (true, true)];
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let n = 16;
+        let raw = (0..n)
+            .map(|_| (Fr::random(&mut rng), rng.next_u32() % 2 == 1))
+            .collect::<Vec<_>>();
+
+        let values = raw
+            .iter()
+            .enumerate()
+            .map(|(i, (v, _))| {
+                AllocatedNum::alloc(cs.namespace(|| format!("value {i}")), || Ok(*v)).unwrap()
+            })
+            .collect::<Vec<_>>();
+        let flags = raw
+            .iter()
+            .enumerate()
+            .map(|(i, (_, f))| match i % 4 {
+                // Mix in constant and negated flags alongside allocated ones.
+                0 => Boolean::constant(*f),
+                1 => Boolean::from(
+                    AllocatedBit::alloc(cs.namespace(|| format!("flag {i}")), Some(!*f)).unwrap(),
+                )
+                .not(),
+                _ => Boolean::from(
+                    AllocatedBit::alloc(cs.namespace(|| format!("flag {i}")), Some(*f)).unwrap(),
+                ),
+            })
+            .collect::<Vec<_>>();
+        let before = cs.num_constraints();
+
+        let sums =
+            AllocatedNum::conditional_scan(cs.namespace(|| "scan"), &values, &flags).unwrap();
 
-        for (c1, c0) in conditions {
-            let mut cs = TestConstraintSystem::<Fr>::new();
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints() - before, n - n / 4);
 
-            let condition0 = Boolean::constant(c0);
-            let condition1 = Boolean::constant(c1);
-            let select = &[condition1, condition0];
+        let mut expected = Fr::ZERO;
+        for ((v, f), sum) in raw.iter().zip(&sums) {
+            if *f {
+                expected += v;
+            }
+            assert_eq!(sum.get_value(), Some(expected));
+            assert_eq!(cs.eval(&sum.lc(Fr::ONE)), Some(expected));
+        }
 
-            let a0 = AllocatedNum::alloc(cs.namespace(|| "alloc a0"), || Ok(Fr::random(&mut rng)))
-                .unwrap();
-            let a1 = AllocatedNum::alloc(cs.namespace(|| "alloc a1"), || Ok(Fr::random(&mut rng)))
-                .unwrap();
-            let a2 = AllocatedNum::alloc(cs.namespace(|| "alloc a2"), || Ok(Fr::random(&mut rng)))
-                .unwrap();
-            let a3 = AllocatedNum::alloc(cs.namespace(|| "alloc a3"), || Ok(Fr::random(&mut rng)))
-                .unwrap();
+        assert!(matches!(
+            AllocatedNum::conditional_scan(cs.namespace(|| "mismatch"), &values, &flags[1..]),
+            Err(SynthesisError::IncompatibleLengthVector(_))
+        ));
+    }
 
-            let res = AllocatedNum::<Fr>::mux_tree(
-                &mut cs.namespace(|| format!("mux tree result for conditions = {c1}, {c0}")),
-                select.iter(),
-                &[a0.clone(), a1.clone(), a2.clone(), a3.clone()],
-            );
-            assert!(res.is_ok());
-            let res = res.unwrap();
+    #[test]
+    fn test_num_affine() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
 
-            let res_expected = match (c1, c0) {
-                (false, false) => a0.clone(),
-                (false, true) => a1.clone(),
-                (true, false) => a2.clone(),
-                (true, true) => a3.clone(),
-            };
-            cs.enforce(
-                || format!("res equality for conditions = {c1}, {c0}"),
-                |lc| lc,
-                |lc| lc,
-                |lc| lc + res_expected.get_variable() - res.get_variable(),
+        for _ in 0..4 {
+            let (x, a, b) = (
+                Fr::random(&mut rng),
+                Fr::random(&mut rng),
+                Fr::random(&mut rng),
             );
+            // Also cover negative coefficients.
+            for (a, b) in [(a, b), (-a, -b)] {
+                let mut cs = TestConstraintSystem::<Fr>::new();
+                let n = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(x)).unwrap();
+                let out = n.affine(cs.namespace(|| "a * x + b"), a, b).unwrap();
 
-            assert!(cs.is_satisfied());
-            assert_eq!(cs.num_constraints(), 4);
+                assert!(cs.is_satisfied());
+                assert_eq!(cs.num_constraints(), 1);
+                assert_eq!(out.get_value().unwrap(), a * x + b);
+
+                cs.set("a * x + b/affine num", a * x);
+                assert_eq!(
+                    cs.which_is_unsatisfied(),
+                    Some("a * x + b/affine constraint")
+                );
+            }
         }
     }
 
     #[test]
-    fn test_num_nonzero() {
-        {
-            let mut cs = TestConstraintSystem::<Fr>::new();
+    fn test_num_scalar_mul() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let mut cs = TestConstraintSystem::<Fr>::new();
 
-            let n = AllocatedNum::alloc(&mut cs, || Ok(Fr::from(3u64))).unwrap();
-            n.assert_nonzero(&mut cs).unwrap();
+        let mut expected = Fr::ZERO;
+        let mut acc = Num::zero();
+        for i in 0..4 {
+            let (v, c) = (Fr::random(&mut rng), Fr::random(&mut rng));
+            let a = AllocatedNum::alloc(cs.namespace(|| format!("a {}", i)), || Ok(v)).unwrap();
 
-            assert!(cs.is_satisfied());
-            cs.set("ephemeral inverse", Fr::from(3u64));
-            assert!(cs.which_is_unsatisfied() == Some("nonzero assertion constraint"));
+            acc = acc.add(&a.scalar_mul(c));
+            expected += v * c;
         }
-        {
-            let mut cs = TestConstraintSystem::<Fr>::new();
+        assert_eq!(cs.num_constraints(), 0);
+        assert_eq!(acc.get_value().unwrap(), expected);
 
-            let n = AllocatedNum::alloc(&mut cs, || Ok(Fr::ZERO)).unwrap();
-            assert!(n.assert_nonzero(&mut cs).is_err());
-        }
+        let sum = AllocatedNum::alloc(cs.namespace(|| "sum"), || acc.value_or_missing()).unwrap();
+        cs.enforce(
+            || "sum constraint",
+            |_| acc.lc(Fr::ONE),
+            |lc| lc + TestConstraintSystem::<Fr>::one(),
+            |lc| lc + sum.get_variable(),
+        );
+        assert!(cs.is_satisfied());
+        assert_eq!(sum.get_value().unwrap(), expected);
+
+        cs.set("sum/num", expected + Fr::ONE);
+        assert_eq!(cs.which_is_unsatisfied(), Some("sum constraint"));
     }
 
     #[test]
-    fn test_into_bits_strict() {
-        let negone = -Fr::ONE;
-
+    fn test_num_eval() {
         let mut cs = TestConstraintSystem::<Fr>::new();
 
-        let n = AllocatedNum::alloc(&mut cs, || Ok(negone)).unwrap();
-        n.to_bits_le_strict(&mut cs).unwrap();
+        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from(3u64))).unwrap();
+        let b = AllocatedNum::alloc_input(cs.namespace(|| "b"), || Ok(Fr::from(5u64))).unwrap();
+        let num = Num::from(a)
+            .scale(Fr::from(2u64))
+            .add(&Num::from(b))
+            .add_bool_with_coeff(
+                TestConstraintSystem::<Fr>::one(),
+                &Boolean::constant(true),
+                Fr::ONE,
+            );
 
-        assert!(cs.is_satisfied());
+        assert_eq!(num.get_value(), Some(Fr::from(12u64)));
+        assert_eq!(cs.eval(&num.lc(Fr::ONE)), num.get_value());
 
-        // make the bit representation the characteristic
-        cs.set("bit 254/boolean", Fr::ONE);
+        // The evaluation follows the assignment, not the cached value.
+        cs.set("a/num", Fr::from(4u64));
+        assert_eq!(cs.eval(&num.lc(Fr::ONE)), Some(Fr::from(14u64)));
+        assert_eq!(num.get_value(), Some(Fr::from(12u64)));
 
-        // this makes the conditional boolean constraint fail
-        assert_eq!(
-            cs.which_is_unsatisfied().unwrap(),
-            "bit 254/boolean constraint"
-        );
+        // Variables from another constraint system cannot be evaluated.
+        let mut other = TestConstraintSystem::<Fr>::new();
+        for i in 0..2 {
+            AllocatedNum::alloc(other.namespace(|| format!("x {i}")), || Ok(Fr::ONE)).unwrap();
+        }
+        let c = AllocatedNum::alloc(other.namespace(|| "c"), || Ok(Fr::ONE)).unwrap();
+        assert_eq!(cs.eval(&Num::from(c).lc(Fr::ONE)), None);
     }
 
     #[test]
-    fn test_into_bits() {
+    fn test_num_add_with_carry() {
         let mut rng = XorShiftRng::from_seed([
             0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
             0xbc, 0xe5,
         ]);
+        let width = 32;
 
-        for i in 0..200 {
-            let r = Fr::random(&mut rng);
-            let mut cs = TestConstraintSystem::<Fr>::new();
-
-            let n = AllocatedNum::alloc(&mut cs, || Ok(r)).unwrap();
+        for (count, carry_width) in [(1, 0), (2, 1), (3, 2), (4, 2), (5, 3)] {
+            let mut values = (0..count)
+                .map(|_| rng.next_u32() as u64)
+                .collect::<Vec<_>>();
+            // Exercise the largest possible carry.
+            values[0] = u32::MAX as u64;
 
-            let bits = if i % 2 == 0 {
-                n.to_bits_le(&mut cs).unwrap()
-            } else {
-                n.to_bits_le_strict(&mut cs).unwrap()
-            };
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let operands = values
+                .iter()
+                .enumerate()
+                .map(|(i, v)| {
+                    Num::from(
+                        AllocatedNum::alloc(cs.namespace(|| format!("a {i}")), || Ok(Fr::from(*v)))
+                            .unwrap(),
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            let (result, carry) =
+                Num::add_with_carry(cs.namespace(|| "sum"), &operands, width).unwrap();
 
             assert!(cs.is_satisfied());
+            assert_eq!(result.len(), width);
+            assert_eq!(carry.len(), carry_width);
 
-            for (i, b) in r.to_le_bits().iter().enumerate() {
-                // `r.to_le_bits()` contains every bit in a representation (including bits which
-                // exceed the field size), whereas the length of `bits` does not exceed the field
-                // size.
-                match bits.get(i) {
-                    Some(Boolean::Is(a)) => assert_eq!(b, a.get_value().unwrap()),
-                    Some(_) => unreachable!(),
-                    None => assert!(!b),
-                };
+            let sum: u64 = values.iter().sum();
+            for (i, bit) in result.iter().chain(&carry).enumerate() {
+                assert_eq!(bit.get_value().unwrap(), (sum >> i) & 1 == 1);
             }
 
-            cs.set("num", Fr::random(&mut rng));
-            assert!(!cs.is_satisfied());
-            cs.set("num", r);
-            assert!(cs.is_satisfied());
-
-            for i in 0..Fr::NUM_BITS {
-                let name = format!("bit {}/boolean", i);
-                let cur = cs.get(&name);
-                let mut tmp = Fr::ONE;
-                tmp.sub_assign(&cur);
-                cs.set(&name, tmp);
-                assert!(!cs.is_satisfied());
-                cs.set(&name, cur);
-                assert!(cs.is_satisfied());
-            }
+            cs.set("sum/bit 0/boolean", Fr::from((sum & 1) ^ 1));
+            assert_eq!(cs.which_is_unsatisfied(), Some("sum/packing constraint"));
         }
     }
 
@@ -1217,4 +5045,46 @@ mod test {
             _ => panic!("unexpected variable type"),
         });
     }
+
+    #[test]
+    fn test_num_sum() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let mut cs = TestConstraintSystem::<Fr>::new();
+
+        let values = (0..4).map(|_| Fr::random(&mut rng)).collect::<Vec<_>>();
+        let nums = values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| AllocatedNum::alloc(cs.namespace(|| format!("a {}", i)), || Ok(*v)))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        let total: Num<Fr> = nums.iter().copied().sum();
+        let collected: Num<Fr> = nums.iter().copied().collect();
+        assert_eq!(cs.num_constraints(), 0);
+        assert_eq!(total.get_value().unwrap(), values.iter().sum::<Fr>());
+        assert_eq!(collected.get_value(), total.get_value());
+
+        // Each number appears in the linear combination with coefficient one.
+        let terms = total.lc.iter().collect::<Vec<_>>();
+        assert_eq!(terms.len(), nums.len());
+        for ((var, coeff), num) in terms.into_iter().zip(&nums) {
+            assert_eq!(var, num.get_variable());
+            assert_eq!(*coeff, Fr::ONE);
+        }
+
+        let empty: Num<Fr> = std::iter::empty::<AllocatedNum<Fr>>().sum();
+        assert_eq!(empty.get_value(), Some(Fr::ZERO));
+
+        // A missing value makes the sum's value missing.
+        let missing = AllocatedNum {
+            value: None,
+            variable: nums[0].get_variable(),
+        };
+        let total: Num<Fr> = nums.iter().copied().chain(Some(missing)).sum();
+        assert_eq!(total.get_value(), None);
+    }
 }
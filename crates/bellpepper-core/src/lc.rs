@@ -19,6 +19,12 @@ impl Variable {
     pub fn get_unchecked(&self) -> Index {
         self.0
     }
+
+    /// Returns the index of the variable, e.g. to bucket variables by
+    /// input or auxiliary position in tooling.
+    pub fn get_index(&self) -> Index {
+        self.get_unchecked()
+    }
 }
 
 /// Represents the index of either an input variable or
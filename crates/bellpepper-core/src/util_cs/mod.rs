@@ -1,8 +1,11 @@
 use crate::LinearCombination;
 use ff::PrimeField;
 
+mod shape;
 pub mod test_cs;
 
+pub use shape::{CircuitShape, ShapeLc, SymbolTable};
+
 pub type Constraint<Scalar> = (
     LinearCombination<Scalar>,
     LinearCombination<Scalar>,
@@ -36,6 +39,22 @@ pub trait Comparable<Scalar: PrimeField> {
     fn aux(&self) -> Vec<String>;
     fn constraints(&self) -> &[Constraint<Scalar>];
 
+    /// Returns the value-free `CircuitShape` of this constraint system,
+    /// suitable for serializing and caching the synthesized R1CS.
+    fn to_shape(&self) -> CircuitShape<Scalar> {
+        CircuitShape::new(
+            self.num_inputs(),
+            self.aux().len(),
+            self.constraints().iter().map(|(a, b, c, _)| (a, b, c)),
+        )
+    }
+
+    /// Returns a `SymbolTable` assigning each variable a compact integer ID
+    /// alongside its namespace path, to accompany an exported shape.
+    fn to_symbol_table(&self) -> SymbolTable {
+        SymbolTable::new(self.inputs(), self.aux())
+    }
+
     fn delta<C: Comparable<Scalar>>(&self, other: &C, ignore_counts: bool) -> Delta<Scalar>
     where
         Scalar: PrimeField,
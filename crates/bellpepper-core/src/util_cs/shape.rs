@@ -0,0 +1,221 @@
+//! Value-free description of a synthesized constraint system.
+
+use ff::PrimeField;
+use serde::{Deserialize, Serialize};
+
+use crate::{Index, LinearCombination, Variable};
+
+/// A linear combination flattened into its `(index, coefficient)` terms.
+pub type ShapeLc<Scalar> = Vec<(Index, Scalar)>;
+
+/// The R1CS structure of a synthesized circuit: its constraints and the
+/// number of input and auxiliary variables, without any assignment. A
+/// `CircuitShape` can be serialized and cached so that later runs can skip
+/// synthesis and hand the shape directly to a backend.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CircuitShape<Scalar: PrimeField> {
+    /// Number of input variables, including the constant `ONE` input.
+    pub num_inputs: usize,
+    /// Number of auxiliary variables.
+    pub num_aux: usize,
+    /// The `(A, B, C)` terms of each constraint `A * B = C`, in order.
+    pub constraints: Vec<(ShapeLc<Scalar>, ShapeLc<Scalar>, ShapeLc<Scalar>)>,
+}
+
+fn lc_to_terms<Scalar: PrimeField>(lc: &LinearCombination<Scalar>) -> ShapeLc<Scalar> {
+    lc.iter()
+        .map(|(var, coeff)| (var.get_unchecked(), *coeff))
+        .collect()
+}
+
+fn terms_to_lc<Scalar: PrimeField>(terms: &ShapeLc<Scalar>) -> LinearCombination<Scalar> {
+    terms
+        .iter()
+        .fold(LinearCombination::zero(), |lc, (index, coeff)| {
+            lc + (*coeff, Variable::new_unchecked(*index))
+        })
+}
+
+impl<Scalar: PrimeField> CircuitShape<Scalar> {
+    /// Builds a shape from a list of constraints and the variable counts.
+    pub fn new<'a, I>(num_inputs: usize, num_aux: usize, constraints: I) -> Self
+    where
+        I: IntoIterator<
+            Item = (
+                &'a LinearCombination<Scalar>,
+                &'a LinearCombination<Scalar>,
+                &'a LinearCombination<Scalar>,
+            ),
+        >,
+    {
+        let constraints = constraints
+            .into_iter()
+            .map(|(a, b, c)| (lc_to_terms(a), lc_to_terms(b), lc_to_terms(c)))
+            .collect();
+
+        CircuitShape {
+            num_inputs,
+            num_aux,
+            constraints,
+        }
+    }
+
+    pub fn num_constraints(&self) -> usize {
+        self.constraints.len()
+    }
+
+    /// Rebuilds the `(A, B, C)` linear combinations of every constraint.
+    pub fn linear_combinations(
+        &self,
+    ) -> Vec<(
+        LinearCombination<Scalar>,
+        LinearCombination<Scalar>,
+        LinearCombination<Scalar>,
+    )> {
+        self.constraints
+            .iter()
+            .map(|(a, b, c)| (terms_to_lc(a), terms_to_lc(b), terms_to_lc(c)))
+            .collect()
+    }
+}
+
+/// Assigns every variable of a circuit a stable integer ID, alongside its
+/// namespace path. Inputs, including `ONE`, take IDs `0..num_inputs` and
+/// auxiliary variables follow, so exports can refer to variables
+/// numerically while keeping them resolvable to names.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SymbolTable {
+    num_inputs: usize,
+    names: Vec<String>,
+}
+
+impl SymbolTable {
+    /// Builds a table from the input and auxiliary variable names, in
+    /// allocation order.
+    pub fn new(inputs: Vec<String>, aux: Vec<String>) -> Self {
+        let num_inputs = inputs.len();
+        let mut names = inputs;
+        names.extend(aux);
+
+        SymbolTable { num_inputs, names }
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Returns the ID of the variable at `index`.
+    pub fn id(&self, index: Index) -> usize {
+        match index {
+            Index::Input(i) => i,
+            Index::Aux(i) => self.num_inputs + i,
+        }
+    }
+
+    /// Returns the variable index with the given ID.
+    pub fn index(&self, id: usize) -> Option<Index> {
+        if id < self.num_inputs {
+            Some(Index::Input(id))
+        } else if id < self.names.len() {
+            Some(Index::Aux(id - self.num_inputs))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the namespace path of the variable with the given ID.
+    pub fn name(&self, id: usize) -> Option<&str> {
+        self.names.get(id).map(String::as_str)
+    }
+
+    /// Iterates over `(id, index, path)` for every variable, by ID.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, Index, &str)> + '_ {
+        self.names.iter().enumerate().map(|(id, name)| {
+            let index = self.index(id).expect("id is in range");
+            (id, index, name.as_str())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_cs::TestConstraintSystem;
+    use crate::{num::AllocatedNum, Comparable, ConstraintSystem};
+
+    use blstrs::Scalar as Fr;
+
+    #[test]
+    fn test_shape_roundtrip() {
+        let mut cs = TestConstraintSystem::<Fr>::new();
+
+        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from(3u64))).unwrap();
+        let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::from(5u64))).unwrap();
+        let c = a.mul(cs.namespace(|| "a * b"), &b).unwrap();
+        let d = c.sub(cs.namespace(|| "c - a"), &a).unwrap();
+        d.inputize(cs.namespace(|| "d")).unwrap();
+
+        assert!(cs.is_satisfied());
+
+        let shape = cs.to_shape();
+        assert_eq!(shape.num_inputs, cs.num_inputs());
+        assert_eq!(shape.num_aux, cs.aux().len());
+        assert_eq!(shape.num_constraints(), cs.num_constraints());
+
+        let bytes = bincode::serialize(&shape).unwrap();
+        let decoded: CircuitShape<Fr> = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(shape, decoded);
+
+        for ((a, b, c), (a_orig, b_orig, c_orig, _)) in
+            decoded.linear_combinations().iter().zip(cs.constraints())
+        {
+            assert!(a.iter().eq(a_orig.iter()));
+            assert!(b.iter().eq(b_orig.iter()));
+            assert!(c.iter().eq(c_orig.iter()));
+        }
+    }
+
+    #[test]
+    fn test_symbol_table() {
+        let mut cs = TestConstraintSystem::<Fr>::new();
+
+        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from(3u64))).unwrap();
+        let b = AllocatedNum::alloc_input(cs.namespace(|| "b"), || Ok(Fr::from(5u64))).unwrap();
+        let c = a.mul(cs.namespace(|| "a * b"), &b).unwrap();
+        c.inputize(cs.namespace(|| "c")).unwrap();
+
+        let symbols = cs.to_symbol_table();
+        assert_eq!(symbols.len(), cs.num_inputs() + cs.aux().len());
+
+        let ids = symbols.iter().map(|(id, _, _)| id).collect::<Vec<_>>();
+        assert_eq!(ids, (0..symbols.len()).collect::<Vec<_>>());
+
+        for (id, index, name) in symbols.iter() {
+            assert_eq!(symbols.id(index), id);
+            assert_eq!(symbols.name(id), Some(name));
+        }
+        assert_eq!(symbols.index(symbols.len()), None);
+
+        assert_eq!(symbols.name(0), Some("ONE"));
+        assert_eq!(
+            symbols.name(symbols.id(a.get_variable().get_unchecked())),
+            Some("a/num")
+        );
+        assert_eq!(
+            symbols.name(symbols.id(b.get_variable().get_unchecked())),
+            Some("b/input num")
+        );
+        assert_eq!(
+            symbols.name(symbols.id(c.get_variable().get_unchecked())),
+            Some("a * b/product num")
+        );
+
+        let bytes = bincode::serialize(&symbols).unwrap();
+        let decoded: SymbolTable = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(symbols, decoded);
+    }
+}
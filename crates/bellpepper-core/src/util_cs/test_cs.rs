@@ -1,3 +1,4 @@
+use std::cell::RefCell;
 use std::cmp::Ordering;
 use std::collections::BTreeMap;
 use std::collections::HashMap;
@@ -30,6 +31,12 @@ pub struct TestConstraintSystem<Scalar: PrimeField> {
     )>,
     inputs: Vec<(Scalar, String)>,
     aux: Vec<(Scalar, String)>,
+    /// Per constraint, whether `which_is_unsatisfied` ever found it
+    /// unsatisfied.
+    coverage: RefCell<Vec<bool>>,
+    /// Per constraint, the category tag it was enforced under with
+    /// `enforce_tagged`, if any.
+    tags: Vec<Option<String>>,
 }
 
 #[derive(Clone, Copy)]
@@ -168,6 +175,8 @@ impl<Scalar: PrimeField> Default for TestConstraintSystem<Scalar> {
             constraints: vec![],
             inputs: vec![(Scalar::ONE, "ONE".into())],
             aux: vec![],
+            coverage: RefCell::new(vec![]),
+            tags: vec![],
         }
     }
 }
@@ -188,6 +197,18 @@ impl<Scalar: PrimeField> TestConstraintSystem<Scalar> {
         self.aux.iter().map(|(scalar, _string)| *scalar).collect()
     }
 
+    /// Evaluates `lc` against the current assignment, or returns `None` if
+    /// it refers to a variable not allocated in this constraint system.
+    pub fn eval(&self, lc: &LinearCombination<Scalar>) -> Option<Scalar> {
+        lc.iter().try_fold(Scalar::ZERO, |acc, (var, coeff)| {
+            let value = match var.get_unchecked() {
+                Index::Input(index) => self.inputs.get(index)?.0,
+                Index::Aux(index) => self.aux.get(index)?.0,
+            };
+            Some(acc + value * coeff)
+        })
+    }
+
     pub fn pretty_print_list(&self) -> Vec<String> {
         let mut result = Vec::new();
 
@@ -205,10 +226,72 @@ impl<Scalar: PrimeField> TestConstraintSystem<Scalar> {
         result
     }
 
+    /// Renders the variables as `pretty_print_list` does, followed by every
+    /// constraint as `name: (A) * (B) = (C)` with variables labelled by path.
     pub fn pretty_print(&self) -> String {
-        let res = self.pretty_print_list();
+        let mut s = String::new();
 
-        res.join("\n")
+        let num_vars = self.inputs.len() + self.aux.len();
+        for line in self.pretty_print_list().into_iter().take(num_vars) {
+            writeln!(s, "{}", line).expect("writing to string never fails");
+        }
+
+        let negone = -Scalar::ONE;
+
+        let powers_of_two = (0..Scalar::NUM_BITS)
+            .map(|i| Scalar::from(2u64).pow_vartime([u64::from(i)]))
+            .collect::<Vec<_>>();
+
+        let pp = |s: &mut String, lc: &LinearCombination<Scalar>| {
+            s.push('(');
+            let mut is_first = true;
+            for (var, coeff) in proc_lc::<Scalar>(lc) {
+                if coeff == negone {
+                    s.push_str(" - ")
+                } else if !is_first {
+                    s.push_str(" + ")
+                }
+                is_first = false;
+
+                if coeff != Scalar::ONE && coeff != negone {
+                    match powers_of_two.iter().position(|x| x == &coeff) {
+                        Some(i) => write!(s, "2^{} . ", i),
+                        None => write!(s, "{:?} . ", coeff),
+                    }
+                    .expect("writing to string never fails");
+                }
+
+                match var.0.get_unchecked() {
+                    Index::Input(i) => {
+                        write!(s, "`I{}`", &self.inputs[i].1)
+                            .expect("writing to string never fails");
+                    }
+                    Index::Aux(i) => {
+                        write!(s, "`A{}`", &self.aux[i].1).expect("writing to string never fails");
+                    }
+                }
+            }
+            if is_first {
+                // Nothing was visited, print 0.
+                s.push('0');
+            }
+            s.push(')');
+        };
+
+        for (a, b, c, name) in &self.constraints {
+            s.push('\n');
+
+            write!(s, "{}: ", name).expect("writing to string never fails");
+            pp(&mut s, a);
+            s.push_str(" * ");
+            pp(&mut s, b);
+            s.push_str(" = ");
+            pp(&mut s, c);
+        }
+
+        s.push('\n');
+
+        s
     }
 
     pub fn hash(&self) -> String {
@@ -237,19 +320,59 @@ impl<Scalar: PrimeField> TestConstraintSystem<Scalar> {
     }
 
     pub fn which_is_unsatisfied(&self) -> Option<&str> {
-        for (a, b, c, path) in &self.constraints {
-            let mut a = eval_lc::<Scalar>(a, &self.inputs, &self.aux);
-            let b = eval_lc::<Scalar>(b, &self.inputs, &self.aux);
-            let c = eval_lc::<Scalar>(c, &self.inputs, &self.aux);
-
-            a.mul_assign(&b);
-
-            if a != c {
-                return Some(path);
+        // Evaluate every constraint, so that coverage is recorded even past
+        // the first unsatisfied one.
+        let mut coverage = self.coverage.borrow_mut();
+        coverage.resize(self.constraints.len(), false);
+
+        let mut first = None;
+        for (i, (a, b, c, path)) in self.constraints.iter().enumerate() {
+            if !self.eval_constraint(a, b, c) {
+                coverage[i] = true;
+                first = first.or(Some(path.as_str()));
             }
         }
 
-        None
+        first
+    }
+
+    fn eval_constraint(
+        &self,
+        a: &LinearCombination<Scalar>,
+        b: &LinearCombination<Scalar>,
+        c: &LinearCombination<Scalar>,
+    ) -> bool {
+        let mut a = eval_lc::<Scalar>(a, &self.inputs, &self.aux);
+        let b = eval_lc::<Scalar>(b, &self.inputs, &self.aux);
+        let c = eval_lc::<Scalar>(c, &self.inputs, &self.aux);
+
+        a.mul_assign(&b);
+
+        a == c
+    }
+
+    /// Returns, for every constraint in order, its path and whether it was
+    /// ever found unsatisfied by `is_satisfied` or `which_is_unsatisfied`.
+    /// Negative tests which `set` a witness should flip the constraint they
+    /// target to `true`.
+    pub fn coverage_report(&self) -> Vec<(String, bool)> {
+        let coverage = self.coverage.borrow();
+
+        self.constraints
+            .iter()
+            .enumerate()
+            .map(|(i, (_, _, _, path))| (path.clone(), coverage.get(i).copied().unwrap_or(false)))
+            .collect()
+    }
+
+    /// Returns whether every constraint enforced with `enforce_tagged`
+    /// under `tag` is satisfied, ignoring all other constraints.
+    pub fn is_satisfied_category(&self, tag: &str) -> bool {
+        self.constraints
+            .iter()
+            .zip(&self.tags)
+            .filter(|(_, t)| t.as_deref() == Some(tag))
+            .all(|((a, b, c, _), _)| self.eval_constraint(a, b, c))
     }
 
     pub fn is_satisfied(&self) -> bool {
@@ -267,6 +390,28 @@ impl<Scalar: PrimeField> TestConstraintSystem<Scalar> {
         self.constraints.len()
     }
 
+    /// Returns the number of nonzero coefficients in the `A`, `B` and `C`
+    /// matrices. Terms on the same variable are already merged inside each
+    /// `LinearCombination`, so terms which cancelled out are not counted.
+    pub fn matrix_density(&self) -> (usize, usize, usize) {
+        let density = |lc: &LinearCombination<Scalar>| {
+            lc.iter()
+                .filter(|(_, coeff)| !bool::from(coeff.is_zero()))
+                .count()
+        };
+
+        self.constraints.iter().fold(
+            (0, 0, 0),
+            |(a_density, b_density, c_density), (a, b, c, _)| {
+                (
+                    a_density + density(a),
+                    b_density + density(b),
+                    c_density + density(c),
+                )
+            },
+        )
+    }
+
     pub fn set(&mut self, path: &str, to: Scalar) {
         match self.named_objects.get(path) {
             Some(NamedObject::Var(v)) => match v.get_unchecked() {
@@ -308,6 +453,36 @@ impl<Scalar: PrimeField> TestConstraintSystem<Scalar> {
         &self.inputs[..]
     }
 
+    /// Returns whether the public input at `path` is assigned `expected`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no input variable exists at `path`.
+    pub fn assert_input_eq(&self, path: &str, expected: Scalar) -> bool {
+        match self.named_objects.get(path) {
+            Some(NamedObject::Var(v)) => match v.get_unchecked() {
+                Index::Input(index) => self.inputs[index].0 == expected,
+                Index::Aux(_) => panic!("variable at path `{}` is not an input", path),
+            },
+            Some(e) => panic!(
+                "tried to get value of path `{}`, but `{:?}` exists there (not a variable)",
+                path, e
+            ),
+            _ => panic!("no variable exists at path: {}", path),
+        }
+    }
+
+    /// Returns the full namespace path `var` was allocated under, or `None`
+    /// if no such variable was allocated in this constraint system.
+    pub fn variable_name(&self, var: Variable) -> Option<String> {
+        let (_, name) = match var.get_unchecked() {
+            Index::Input(index) => self.inputs.get(index)?,
+            Index::Aux(index) => self.aux.get(index)?,
+        };
+
+        Some(name.clone())
+    }
+
     pub fn get(&mut self, path: &str) -> Scalar {
         match self.named_objects.get(path) {
             Some(NamedObject::Var(v)) => match v.get_unchecked() {
@@ -424,6 +599,21 @@ impl<Scalar: PrimeField> ConstraintSystem<Scalar> for TestConstraintSystem<Scala
         let c = c(LinearCombination::zero());
 
         self.constraints.push((a, b, c, path));
+        self.tags.push(None);
+    }
+
+    fn enforce_tagged<A, AR, LA, LB, LC>(&mut self, tag: &str, annotation: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+        LB: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+        LC: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+    {
+        self.enforce(annotation, a, b, c);
+        if let Some(last) = self.tags.last_mut() {
+            *last = Some(tag.to_string());
+        }
     }
 
     fn push_namespace<NR, N>(&mut self, name_fn: N)
@@ -444,6 +634,18 @@ impl<Scalar: PrimeField> ConstraintSystem<Scalar> for TestConstraintSystem<Scala
     fn get_root(&mut self) -> &mut Self::Root {
         self
     }
+
+    fn num_constraints(&self) -> usize {
+        self.constraints.len()
+    }
+
+    fn num_inputs(&self) -> usize {
+        self.inputs.len()
+    }
+
+    fn num_aux(&self) -> usize {
+        self.aux.len()
+    }
 }
 
 #[cfg(test)]
@@ -508,4 +710,264 @@ mod tests {
 
         assert!(cs.get("test1/test2/hehe") == Fr::ONE);
     }
+
+    #[test]
+    fn test_pretty_print() {
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let one = TestConstraintSystem::<Fr>::one();
+        let a = cs
+            .namespace(|| "a")
+            .alloc(|| "var", || Ok(Fr::from(3u64)))
+            .unwrap();
+        let b = cs.alloc(|| "b", || Ok(Fr::from(5u64))).unwrap();
+        let c = cs.alloc_input(|| "c", || Ok(Fr::from(15u64))).unwrap();
+
+        cs.enforce(|| "mult", |lc| lc + a, |lc| lc + b, |lc| lc + c);
+        cs.enforce(
+            || "linear",
+            |lc| lc + a + (Fr::from(4u64), b) - c,
+            |lc| lc + one,
+            |lc| lc,
+        );
+
+        let printed = cs.pretty_print();
+        assert_eq!(printed, cs.pretty_print());
+        assert!(printed.contains("INPUT c"));
+        assert!(printed.contains("mult: (`Aa/var`) * (`Ab`) = (`Ic`)"));
+        assert!(printed.contains("linear: ( - `Ic` + `Aa/var` + 2^2 . `Ab`) * (`IONE`) = (0)"));
+        assert!(printed.starts_with("INPUT ONE\nINPUT c\nAUX a/var\nAUX b\n"));
+    }
+
+    #[test]
+    fn test_with_namespace() {
+        let mut cs = TestConstraintSystem::<Fr>::new();
+
+        let a = cs.with_namespace("outer", |cs| {
+            let a = cs.alloc(|| "a", || Ok(Fr::from(3u64))).unwrap();
+            cs.with_namespace("inner", |cs| {
+                cs.enforce(
+                    || "a is 3",
+                    |lc| lc + a,
+                    |lc| lc + TestConstraintSystem::<Fr>::one(),
+                    |lc| lc + (Fr::from(3u64), TestConstraintSystem::<Fr>::one()),
+                );
+            });
+            a
+        });
+
+        let b = cs.alloc(|| "b", || Ok(Fr::from(5u64))).unwrap();
+        cs.enforce(|| "mult", |lc| lc + a, |lc| lc + b, |lc| lc);
+
+        assert_eq!(cs.get("outer/a"), Fr::from(3u64));
+        assert_eq!(cs.get("b"), Fr::from(5u64));
+        assert_eq!(cs.which_is_unsatisfied(), Some("mult"));
+
+        cs.set("b", Fr::ZERO);
+        assert!(cs.is_satisfied());
+        cs.set("outer/a", Fr::ONE);
+        assert_eq!(cs.which_is_unsatisfied(), Some("outer/inner/a is 3"));
+    }
+
+    #[test]
+    fn test_enforce_lc() {
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let a = cs.alloc(|| "a", || Ok(Fr::from(3u64))).unwrap();
+        let b = cs.alloc(|| "b", || Ok(Fr::from(5u64))).unwrap();
+        let c = cs.alloc(|| "c", || Ok(Fr::from(15u64))).unwrap();
+
+        cs.enforce_lc(
+            || "mult",
+            LinearCombination::from_variable(a),
+            LinearCombination::from_variable(b),
+            LinearCombination::from_variable(c),
+        );
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), 1);
+
+        cs.set("c", Fr::from(16u64));
+        assert_eq!(cs.which_is_unsatisfied(), Some("mult"));
+    }
+
+    #[test]
+    fn test_coverage_report() {
+        let mut cs = TestConstraintSystem::<Fr>::new();
+
+        let a = cs.alloc(|| "a", || Ok(Fr::from(3u64))).unwrap();
+        let b = cs.alloc(|| "b", || Ok(Fr::from(4u64))).unwrap();
+        let c = cs.alloc(|| "c", || Ok(Fr::from(12u64))).unwrap();
+        let d = cs.alloc(|| "d", || Ok(Fr::from(7u64))).unwrap();
+        cs.enforce(|| "mult", |lc| lc + a, |lc| lc + b, |lc| lc + c);
+        cs.enforce(
+            || "add",
+            |lc| lc + a + b,
+            |lc| lc + TestConstraintSystem::<Fr>::one(),
+            |lc| lc + d,
+        );
+
+        assert!(cs.is_satisfied());
+        assert_eq!(
+            cs.coverage_report(),
+            vec![("mult".to_string(), false), ("add".to_string(), false)]
+        );
+
+        cs.set("d", Fr::from(8u64));
+        assert!(!cs.is_satisfied());
+        assert_eq!(
+            cs.coverage_report(),
+            vec![("mult".to_string(), false), ("add".to_string(), true)]
+        );
+
+        // Restoring the witness keeps the recorded coverage.
+        cs.set("d", Fr::from(7u64));
+        cs.set("c", Fr::from(13u64));
+        assert_eq!(cs.which_is_unsatisfied(), Some("mult"));
+        assert_eq!(
+            cs.coverage_report(),
+            vec![("mult".to_string(), true), ("add".to_string(), true)]
+        );
+    }
+
+    #[test]
+    fn test_variable_get_index() {
+        use std::collections::HashMap;
+
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let a = cs.alloc(|| "a", || Ok(Fr::ONE)).unwrap();
+        let b = cs.alloc_input(|| "b", || Ok(Fr::ONE)).unwrap();
+        let c = cs.alloc(|| "c", || Ok(Fr::ONE)).unwrap();
+
+        assert_eq!(
+            TestConstraintSystem::<Fr>::one().get_index(),
+            Index::Input(0)
+        );
+        assert_eq!(a.get_index(), Index::Aux(0));
+        assert_eq!(b.get_index(), Index::Input(1));
+        assert_eq!(c.get_index(), Index::Aux(1));
+
+        let mut buckets = HashMap::new();
+        for var in [a, b, c, a] {
+            *buckets.entry(var.get_index()).or_insert(0) += 1;
+        }
+        assert_eq!(buckets.len(), 3);
+        assert_eq!(buckets[&Index::Aux(0)], 2);
+    }
+
+    #[test]
+    fn test_assert_input_eq() {
+        use crate::gadgets::num::AllocatedNum;
+
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from(5u64))).unwrap();
+        a.inputize(cs.namespace(|| "a input")).unwrap();
+
+        assert!(cs.assert_input_eq("a input/input variable", Fr::from(5u64)));
+        assert!(!cs.assert_input_eq("a input/input variable", Fr::from(6u64)));
+    }
+
+    #[test]
+    #[should_panic(expected = "is not an input")]
+    fn test_assert_input_eq_aux() {
+        use crate::gadgets::num::AllocatedNum;
+
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::from(5u64))).unwrap();
+
+        cs.assert_input_eq("a/num", Fr::from(5u64));
+    }
+
+    #[test]
+    fn test_is_satisfied_category() {
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let a = cs.alloc(|| "a", || Ok(Fr::ONE)).unwrap();
+        let b = cs.alloc(|| "b", || Ok(Fr::from(2u64))).unwrap();
+
+        {
+            let mut cs = cs.namespace(|| "gadget");
+            cs.enforce_tagged(
+                "range check",
+                || "a is boolean",
+                |lc| lc + a,
+                |lc| lc + TestConstraintSystem::<Fr>::one() - a,
+                |lc| lc,
+            );
+            cs.enforce_tagged(
+                "arithmetic",
+                || "b = 2a",
+                |lc| lc + a + a,
+                |lc| lc + TestConstraintSystem::<Fr>::one(),
+                |lc| lc + b,
+            );
+        }
+        cs.enforce(
+            || "untagged",
+            |lc| lc + a,
+            |lc| lc + TestConstraintSystem::<Fr>::one(),
+            |lc| lc + a,
+        );
+
+        assert!(cs.is_satisfied_category("range check"));
+        assert!(cs.is_satisfied_category("arithmetic"));
+
+        cs.set("b", Fr::from(3u64));
+        assert!(cs.is_satisfied_category("range check"));
+        assert!(!cs.is_satisfied_category("arithmetic"));
+
+        cs.set("b", Fr::from(4u64));
+        cs.set("a", Fr::from(2u64));
+        assert!(!cs.is_satisfied_category("range check"));
+        assert!(cs.is_satisfied_category("arithmetic"));
+
+        // Untagged constraints and unknown categories are never checked.
+        assert!(cs.is_satisfied_category("unknown"));
+    }
+
+    #[test]
+    fn test_variable_name() {
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let a = cs
+            .namespace(|| "gadget")
+            .alloc(|| "a", || Ok(Fr::ONE))
+            .unwrap();
+        let b = cs.alloc_input(|| "b", || Ok(Fr::ONE)).unwrap();
+
+        assert_eq!(cs.variable_name(a).as_deref(), Some("gadget/a"));
+        assert_eq!(cs.variable_name(b).as_deref(), Some("b"));
+        assert_eq!(
+            cs.variable_name(TestConstraintSystem::<Fr>::one())
+                .as_deref(),
+            Some("ONE")
+        );
+        assert_eq!(
+            cs.variable_name(Variable::new_unchecked(Index::Aux(1))),
+            None
+        );
+    }
+
+    #[test]
+    fn test_matrix_density() {
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let a = cs.alloc(|| "a", || Ok(Fr::from(3u64))).unwrap();
+        let b = cs.alloc(|| "b", || Ok(Fr::from(5u64))).unwrap();
+        let c = cs.alloc(|| "c", || Ok(Fr::from(15u64))).unwrap();
+        let one = TestConstraintSystem::<Fr>::one();
+
+        // (a + b) * b = c + b, with `b` cancelling out of `C`.
+        cs.enforce(
+            || "first",
+            |lc| lc + a + b,
+            |lc| lc + b,
+            |lc| lc + c + b - b,
+        );
+        // a * 1 = 3
+        cs.enforce(
+            || "second",
+            |lc| lc + a,
+            |lc| lc + one,
+            |lc| lc + (Fr::from(3u64), one),
+        );
+        // Repeated terms on the same variable merge into one entry.
+        cs.enforce(|| "third", |lc| lc + a + a, |lc| lc, |lc| lc);
+
+        assert_eq!(cs.matrix_density(), (4, 2, 2));
+    }
 }
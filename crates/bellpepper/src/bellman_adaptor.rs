@@ -0,0 +1,297 @@
+//! Adaptor for synthesizing bellpepper circuits with bellman.
+
+use std::io;
+
+use bellpepper_core::{
+    Circuit, ConstraintSystem, Index, LinearCombination, SynthesisError, Variable,
+};
+use ff::PrimeField;
+
+/// Wraps a bellpepper `Circuit` so that it implements bellman's `Circuit`
+/// trait, e.g. to be proven with bellman's Groth16 prover.
+#[derive(Debug, Clone)]
+pub struct BellmanAdaptor<C>(pub C);
+
+impl<Scalar, C> bellman::Circuit<Scalar> for BellmanAdaptor<C>
+where
+    Scalar: PrimeField,
+    C: Circuit<Scalar>,
+{
+    fn synthesize<CS: bellman::ConstraintSystem<Scalar>>(
+        self,
+        cs: &mut CS,
+    ) -> Result<(), bellman::SynthesisError> {
+        let mut recorder = BellmanCS::default();
+        self.0.synthesize(&mut recorder).map_err(to_bellman_error)?;
+        recorder.replay(cs)
+    }
+}
+
+/// A bellpepper `ConstraintSystem` recording every call, to be replayed into
+/// a bellman one.
+///
+/// bellpepper requires constraint systems to be `Send` while bellman does
+/// not, so the bellman constraint system cannot be wrapped directly. Value
+/// closures are evaluated while recording, and their results are only
+/// handed to bellman when it asks for them during the replay.
+#[derive(Debug)]
+struct BellmanCS<Scalar: PrimeField> {
+    ops: Vec<Op<Scalar>>,
+    #[allow(clippy::type_complexity)]
+    constraints: Vec<(
+        LinearCombination<Scalar>,
+        LinearCombination<Scalar>,
+        LinearCombination<Scalar>,
+        String,
+    )>,
+    num_inputs: usize,
+    num_aux: usize,
+}
+
+#[derive(Debug)]
+enum Op<Scalar: PrimeField> {
+    Alloc(String, Result<Scalar, SynthesisError>),
+    AllocInput(String, Result<Scalar, SynthesisError>),
+    /// The next entry of `BellmanCS::constraints`.
+    Enforce,
+    PushNamespace(String),
+    PopNamespace,
+}
+
+impl<Scalar: PrimeField> Default for BellmanCS<Scalar> {
+    fn default() -> Self {
+        BellmanCS {
+            ops: vec![],
+            constraints: vec![],
+            num_inputs: 1,
+            num_aux: 0,
+        }
+    }
+}
+
+impl<Scalar: PrimeField> BellmanCS<Scalar> {
+    /// Replays the recorded calls into `cs`, mapping each recorded variable
+    /// to the one `cs` allocates for it.
+    fn replay<CS: bellman::ConstraintSystem<Scalar>>(
+        self,
+        cs: &mut CS,
+    ) -> Result<(), bellman::SynthesisError> {
+        let mut inputs = vec![CS::one()];
+        let mut aux = vec![];
+        let mut constraints = self.constraints.into_iter();
+
+        for op in self.ops {
+            match op {
+                Op::Alloc(annotation, value) => {
+                    aux.push(cs.alloc(|| annotation, || value.map_err(to_bellman_error))?);
+                }
+                Op::AllocInput(annotation, value) => {
+                    inputs.push(cs.alloc_input(|| annotation, || value.map_err(to_bellman_error))?);
+                }
+                Op::Enforce => {
+                    let (a, b, c, annotation) = constraints.next().unwrap();
+                    cs.enforce(
+                        || annotation,
+                        |lc| lc + &to_bellman_lc(&a, &inputs, &aux),
+                        |lc| lc + &to_bellman_lc(&b, &inputs, &aux),
+                        |lc| lc + &to_bellman_lc(&c, &inputs, &aux),
+                    );
+                }
+                Op::PushNamespace(name) => {
+                    bellman::ConstraintSystem::push_namespace(cs.get_root(), || name)
+                }
+                Op::PopNamespace => bellman::ConstraintSystem::pop_namespace(cs.get_root()),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<Scalar: PrimeField> ConstraintSystem<Scalar> for BellmanCS<Scalar> {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<Scalar, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.ops.push(Op::Alloc(annotation().into(), f()));
+        self.num_aux += 1;
+
+        Ok(Variable::new_unchecked(Index::Aux(self.num_aux - 1)))
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<Scalar, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.ops.push(Op::AllocInput(annotation().into(), f()));
+        self.num_inputs += 1;
+
+        Ok(Variable::new_unchecked(Index::Input(self.num_inputs - 1)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, annotation: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+        LB: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+        LC: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+    {
+        self.ops.push(Op::Enforce);
+        self.constraints.push((
+            a(LinearCombination::zero()),
+            b(LinearCombination::zero()),
+            c(LinearCombination::zero()),
+            annotation().into(),
+        ));
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.ops.push(Op::PushNamespace(name_fn().into()));
+    }
+
+    fn pop_namespace(&mut self) {
+        self.ops.push(Op::PopNamespace);
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+
+    fn num_constraints(&self) -> usize {
+        self.constraints.len()
+    }
+
+    fn num_inputs(&self) -> usize {
+        self.num_inputs
+    }
+
+    fn num_aux(&self) -> usize {
+        self.num_aux
+    }
+}
+
+fn to_bellman_lc<Scalar: PrimeField>(
+    lc: &LinearCombination<Scalar>,
+    inputs: &[bellman::Variable],
+    aux: &[bellman::Variable],
+) -> bellman::LinearCombination<Scalar> {
+    lc.iter()
+        .fold(bellman::LinearCombination::zero(), |acc, (var, coeff)| {
+            let var = match var.get_unchecked() {
+                Index::Input(i) => inputs[i],
+                Index::Aux(i) => aux[i],
+            };
+            acc + (*coeff, var)
+        })
+}
+
+/// Maps a bellpepper error to bellman. Errors bellman has no variant for
+/// are carried as an `IoError` with the original message.
+fn to_bellman_error(e: SynthesisError) -> bellman::SynthesisError {
+    match e {
+        SynthesisError::AssignmentMissing => bellman::SynthesisError::AssignmentMissing,
+        SynthesisError::DivisionByZero => bellman::SynthesisError::DivisionByZero,
+        SynthesisError::Unsatisfiable => bellman::SynthesisError::Unsatisfiable,
+        SynthesisError::PolynomialDegreeTooLarge => {
+            bellman::SynthesisError::PolynomialDegreeTooLarge
+        }
+        SynthesisError::UnexpectedIdentity => bellman::SynthesisError::UnexpectedIdentity,
+        SynthesisError::IoError(e) => bellman::SynthesisError::IoError(e),
+        SynthesisError::UnconstrainedVariable => bellman::SynthesisError::UnconstrainedVariable,
+        e => bellman::SynthesisError::IoError(io::Error::new(io::ErrorKind::Other, e.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bellman::gadgets::test::TestConstraintSystem as BellmanTestCS;
+    use bellpepper_core::num::AllocatedNum;
+    use bellpepper_core::test_cs::TestConstraintSystem;
+    use blstrs::Scalar as Fr;
+
+    struct CubeCircuit {
+        x: Option<Fr>,
+    }
+
+    impl Circuit<Fr> for CubeCircuit {
+        fn synthesize<CS: ConstraintSystem<Fr>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let x = AllocatedNum::alloc(cs.namespace(|| "x"), || {
+                self.x.ok_or(SynthesisError::AssignmentMissing)
+            })?;
+            let x2 = x.square(cs.namespace(|| "x^2"))?;
+            let x3 = x2.mul(cs.namespace(|| "x^3"), &x)?;
+            let out = x3.add(cs.namespace(|| "x^3 + x"), &x)?;
+            out.inputize(cs.namespace(|| "out"))
+        }
+    }
+
+    #[test]
+    fn test_bellman_adaptor() {
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        CubeCircuit {
+            x: Some(Fr::from(3u64)),
+        }
+        .synthesize(&mut cs)
+        .unwrap();
+
+        let mut bellman_cs = BellmanTestCS::<Fr>::new();
+        bellman::Circuit::synthesize(
+            BellmanAdaptor(CubeCircuit {
+                x: Some(Fr::from(3u64)),
+            }),
+            &mut bellman_cs,
+        )
+        .unwrap();
+
+        assert!(cs.is_satisfied());
+        assert!(bellman_cs.is_satisfied());
+        assert_eq!(bellman_cs.num_constraints(), cs.num_constraints());
+        assert_eq!(bellman_cs.num_inputs(), cs.num_inputs());
+        assert!(bellman_cs.verify(&[Fr::from(30u64)]));
+        assert_eq!(bellman_cs.get("x^3/product num"), Fr::from(27u64));
+
+        bellman_cs.set("x^3/product num", Fr::from(28u64));
+        assert_eq!(
+            bellman_cs.which_is_unsatisfied(),
+            Some("x^3/multiplication constraint")
+        );
+
+        let mut bellman_cs = BellmanTestCS::<Fr>::new();
+        let res =
+            bellman::Circuit::synthesize(BellmanAdaptor(CubeCircuit { x: None }), &mut bellman_cs);
+        assert!(matches!(
+            res,
+            Err(bellman::SynthesisError::AssignmentMissing)
+        ));
+    }
+
+    #[test]
+    fn test_bellman_cs_counts() {
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        CubeCircuit {
+            x: Some(Fr::from(3u64)),
+        }
+        .synthesize(&mut cs)
+        .unwrap();
+
+        // Recording does not need the witness.
+        let mut recorder = BellmanCS::<Fr>::default();
+        CubeCircuit { x: None }.synthesize(&mut recorder).unwrap();
+
+        assert_eq!(recorder.num_constraints(), cs.num_constraints());
+        assert_eq!(recorder.num_inputs(), cs.num_inputs());
+        assert_eq!(recorder.num_aux(), cs.num_aux());
+    }
+}
@@ -0,0 +1,168 @@
+//! Unsigned fixed-point arithmetic over allocated numbers.
+
+use ff::{PrimeField, PrimeFieldBits};
+
+use crate::gadgets::num::AllocatedNum;
+use bellpepper_core::{ConstraintSystem, LinearCombination, SynthesisError};
+
+/// An unsigned fixed-point number with `F` fractional bits. The allocated
+/// number holds the raw integer `x * 2^F`.
+#[derive(Debug, Clone)]
+pub struct FixedPoint<Scalar: PrimeField, const F: usize> {
+    num: AllocatedNum<Scalar>,
+}
+
+impl<Scalar: PrimeField, const F: usize> FixedPoint<Scalar, F> {
+    /// Wraps an allocated number holding the raw integer `x * 2^F`.
+    pub fn from_num(num: AllocatedNum<Scalar>) -> Self {
+        FixedPoint { num }
+    }
+
+    /// Allocates a fixed-point number from its raw integer `x * 2^F`.
+    pub fn alloc<CS, V>(cs: CS, raw: V) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+        V: FnOnce() -> Result<Scalar, SynthesisError>,
+    {
+        Ok(FixedPoint {
+            num: AllocatedNum::alloc(cs, raw)?,
+        })
+    }
+
+    pub fn num(&self) -> &AllocatedNum<Scalar> {
+        &self.num
+    }
+
+    /// Returns the raw integer `x * 2^F`, if known.
+    pub fn get_value(&self) -> Option<Scalar> {
+        self.num.get_value()
+    }
+
+    /// Returns `self + other`. The sum is exact, so no rescaling is needed.
+    pub fn add<CS>(&self, cs: CS, other: &Self) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+    {
+        Ok(FixedPoint {
+            num: self.num.add(cs, &other.num)?,
+        })
+    }
+
+    /// Returns `self * other`, truncated to `F` fractional bits. The raw
+    /// product `q * 2^F + r` is split into a quotient `q` of `n` bits and a
+    /// remainder `r` of `F` bits, both range-checked so that the truncation
+    /// is unique. Callers must choose `n` large enough for the result.
+    pub fn mul<CS>(&self, mut cs: CS, other: &Self, n: usize) -> Result<Self, SynthesisError>
+    where
+        CS: ConstraintSystem<Scalar>,
+        Scalar: PrimeFieldBits,
+    {
+        assert!(n + F < Scalar::CAPACITY as usize);
+
+        let split = match (self.num.get_value(), other.num.get_value()) {
+            (Some(a), Some(b)) => {
+                let bits = (a * b).to_le_bits();
+                let remainder = from_le_bits::<Scalar>(bits.iter().take(F).map(|b| *b));
+                let quotient = from_le_bits::<Scalar>(bits.iter().skip(F).take(n).map(|b| *b));
+                Some((quotient, remainder))
+            }
+            _ => None,
+        };
+
+        let quotient = AllocatedNum::alloc(cs.namespace(|| "quotient"), || {
+            split
+                .map(|(q, _)| q)
+                .ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let remainder = AllocatedNum::alloc(cs.namespace(|| "remainder"), || {
+            split
+                .map(|(_, r)| r)
+                .ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        quotient.to_n_bits_le(cs.namespace(|| "quotient range check"), n)?;
+        remainder.to_n_bits_le(cs.namespace(|| "remainder range check"), F)?;
+
+        let scale = Scalar::from(2).pow_vartime([F as u64]);
+
+        // Constrain: a * b = quotient * 2^F + remainder
+        cs.enforce(
+            || "truncation constraint",
+            |lc| lc + self.num.get_variable(),
+            |lc| lc + other.num.get_variable(),
+            |_| {
+                LinearCombination::zero()
+                    + (scale, quotient.get_variable())
+                    + remainder.get_variable()
+            },
+        );
+
+        Ok(FixedPoint { num: quotient })
+    }
+}
+
+fn from_le_bits<Scalar: PrimeField>(bits: impl DoubleEndedIterator<Item = bool>) -> Scalar {
+    bits.rev().fold(Scalar::ZERO, |acc, bit| {
+        let acc = acc.double();
+        if bit {
+            acc + Scalar::ONE
+        } else {
+            acc
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bellpepper_core::test_cs::*;
+    use blstrs::Scalar as Fr;
+    use ff::Field;
+
+    #[test]
+    fn test_fixed_point_mul() {
+        // (raw a, raw b, raw a * b >> 8), with 8 fractional bits.
+        let cases = [
+            // 3.5 * 2.25 = 7.875
+            (896u64, 576u64, 2016u64),
+            // 1.5 * 1.5 = 2.25
+            (384, 384, 576),
+            // 2^-8 * 2^-8 truncates to 0
+            (1, 1, 0),
+            // 255.99609375 * 1 = 255.99609375
+            (65535, 256, 65535),
+        ];
+
+        for (a_val, b_val, expected) in cases {
+            let mut cs = TestConstraintSystem::<Fr>::new();
+            let a =
+                FixedPoint::<Fr, 8>::alloc(cs.namespace(|| "a"), || Ok(Fr::from(a_val))).unwrap();
+            let b =
+                FixedPoint::<Fr, 8>::alloc(cs.namespace(|| "b"), || Ok(Fr::from(b_val))).unwrap();
+
+            let c = a.mul(cs.namespace(|| "a * b"), &b, 16).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(c.get_value(), Some(Fr::from(expected)));
+
+            // Shifting one unit between quotient and remainder breaks the
+            // remainder's range check.
+            let remainder = cs.get("a * b/remainder/num");
+            cs.set("a * b/quotient/num", Fr::from(expected) - Fr::ONE);
+            cs.set("a * b/remainder/num", remainder + Fr::from(256u64));
+            assert!(!cs.is_satisfied());
+        }
+    }
+
+    #[test]
+    fn test_fixed_point_add() {
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let a = FixedPoint::<Fr, 8>::alloc(cs.namespace(|| "a"), || Ok(Fr::from(896u64))).unwrap();
+        let b = FixedPoint::<Fr, 8>::alloc(cs.namespace(|| "b"), || Ok(Fr::from(576u64))).unwrap();
+
+        let c = a.add(cs.namespace(|| "a + b"), &b).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(c.get_value(), Some(Fr::from(1472u64)));
+    }
+}
@@ -117,6 +117,73 @@ where
     Ok((res_x, res_y))
 }
 
+/// Performs a 2-bit window table lookup. `bits` is in
+/// little-endian order.
+pub fn lookup2_xy<Scalar: PrimeField, CS>(
+    mut cs: CS,
+    bits: &[Boolean],
+    coords: &[(Scalar, Scalar)],
+) -> Result<(AllocatedNum<Scalar>, AllocatedNum<Scalar>), SynthesisError>
+where
+    CS: ConstraintSystem<Scalar>,
+{
+    assert_eq!(bits.len(), 2);
+    assert_eq!(coords.len(), 4);
+
+    // Calculate the index into `coords`
+    let i = match (bits[0].get_value(), bits[1].get_value()) {
+        (Some(a_value), Some(b_value)) => {
+            let mut tmp = 0;
+            if a_value {
+                tmp += 1;
+            }
+            if b_value {
+                tmp += 2;
+            }
+            Some(tmp)
+        }
+        _ => None,
+    };
+
+    // Allocate the x-coordinate resulting from the lookup
+    let res_x = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(coords[*i.get()?].0))?;
+
+    // Allocate the y-coordinate resulting from the lookup
+    let res_y = AllocatedNum::alloc(cs.namespace(|| "y"), || Ok(coords[*i.get()?].1))?;
+
+    // Compute the coefficients for the lookup constraints
+    let mut x_coeffs = [Scalar::ZERO; 4];
+    let mut y_coeffs = [Scalar::ZERO; 4];
+    synth::<Scalar, _>(2, coords.iter().map(|c| &c.0), &mut x_coeffs);
+    synth::<Scalar, _>(2, coords.iter().map(|c| &c.1), &mut y_coeffs);
+
+    let one = CS::one();
+
+    cs.enforce(
+        || "x-coordinate lookup",
+        |lc| lc + (x_coeffs[0b01], one) + &bits[1].lc::<Scalar>(one, x_coeffs[0b11]),
+        |lc| lc + &bits[0].lc::<Scalar>(one, Scalar::ONE),
+        |lc| {
+            lc + res_x.get_variable()
+                - (x_coeffs[0b00], one)
+                - &bits[1].lc::<Scalar>(one, x_coeffs[0b10])
+        },
+    );
+
+    cs.enforce(
+        || "y-coordinate lookup",
+        |lc| lc + (y_coeffs[0b01], one) + &bits[1].lc::<Scalar>(one, y_coeffs[0b11]),
+        |lc| lc + &bits[0].lc::<Scalar>(one, Scalar::ONE),
+        |lc| {
+            lc + res_y.get_variable()
+                - (y_coeffs[0b00], one)
+                - &bits[1].lc::<Scalar>(one, y_coeffs[0b10])
+        },
+    );
+
+    Ok((res_x, res_y))
+}
+
 /// Performs a 3-bit window table lookup, where
 /// one of the bits is a sign bit.
 pub fn lookup3_xy_with_conditional_negation<Scalar: PrimeField, CS>(
@@ -186,6 +253,99 @@ where
     Ok((x, y.into()))
 }
 
+/// Looks up the entry of a constant `table` at the index encoded by
+/// `index_bits`, in little-endian order. The table is padded with zeros
+/// to `2^index_bits.len()` entries. Because the entries are constants,
+/// the first bit selects between them for free and the lookup costs
+/// `2^(index_bits.len() - 1) - 1` constraints (one for a single bit).
+pub fn lookup<Scalar: PrimeField, CS>(
+    mut cs: CS,
+    index_bits: &[Boolean],
+    table: &[Scalar],
+) -> Result<AllocatedNum<Scalar>, SynthesisError>
+where
+    CS: ConstraintSystem<Scalar>,
+{
+    let size = 1 << index_bits.len();
+    if table.len() > size {
+        return Err(SynthesisError::IncompatibleLengthVector(format!(
+            "table has {} entries but {} index bits address only {}",
+            table.len(),
+            index_bits.len(),
+            size
+        )));
+    }
+    let mut table = table.to_vec();
+    table.resize(size, Scalar::ZERO);
+
+    let (first, rest) = match index_bits.split_first() {
+        Some(split) => split,
+        None => return AllocatedNum::alloc_constant(cs.namespace(|| "constant"), table[0]),
+    };
+
+    let one = CS::one();
+
+    // Selecting between two constants with the first bit is linear.
+    let mut level: Vec<Num<Scalar>> = table
+        .chunks(2)
+        .map(|pair| {
+            Num::zero()
+                .add_bool_with_coeff(one, &Boolean::constant(true), pair[0])
+                .add_bool_with_coeff(one, first, pair[1] - pair[0])
+        })
+        .collect();
+
+    let mut result = None;
+    for (i, bit) in rest.iter().enumerate() {
+        let mut next = Vec::with_capacity(level.len() / 2);
+
+        for (j, pair) in level.chunks(2).enumerate() {
+            let (lo, hi) = (&pair[0], &pair[1]);
+            let mut cs = cs.namespace(|| format!("bit {} entry {}", i + 1, j));
+
+            let out = AllocatedNum::alloc(cs.namespace(|| "select"), || {
+                if *bit.get_value().get()? {
+                    Ok(*hi.get_value().get()?)
+                } else {
+                    Ok(*lo.get_value().get()?)
+                }
+            })?;
+
+            // Constrain: bit * (hi - lo) = out - lo
+            cs.enforce(
+                || "selection constraint",
+                |_| bit.lc(one, Scalar::ONE),
+                |_| hi.lc(Scalar::ONE) - &lo.lc(Scalar::ONE),
+                |_| lo.lc(-Scalar::ONE) + out.get_variable(),
+            );
+
+            next.push(out);
+        }
+
+        level = next.iter().map(|num| Num::from(*num)).collect();
+        result = next.pop();
+    }
+
+    match result {
+        Some(result) => Ok(result),
+        None => {
+            let entry = &level[0];
+            let result = AllocatedNum::alloc(cs.namespace(|| "result"), || {
+                Ok(*entry.get_value().get()?)
+            })?;
+
+            cs.enforce(
+                || "lookup",
+                |_| entry.lc(Scalar::ONE),
+                |lc| lc + one,
+                |lc| lc + result.get_variable(),
+            );
+
+            Ok(result)
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -241,6 +401,41 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_lookup2_xy() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let points: Vec<(Fr, Fr)> = (0..4)
+            .map(|_| (Fr::random(&mut rng), Fr::random(&mut rng)))
+            .collect();
+
+        for index in 0..4 {
+            let mut cs = TestConstraintSystem::<Fr>::new();
+
+            let a_val = index & 1 == 1;
+            let a = Boolean::from(AllocatedBit::alloc(cs.namespace(|| "a"), Some(a_val)).unwrap());
+
+            let b_val = index & 2 == 2;
+            let b = Boolean::from(AllocatedBit::alloc(cs.namespace(|| "b"), Some(b_val)).unwrap());
+
+            let bits = vec![a, b];
+
+            let res = lookup2_xy(&mut cs, &bits, &points).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(cs.num_constraints(), 4);
+
+            assert_eq!(res.0.get_value().unwrap(), points[index].0);
+            assert_eq!(res.1.get_value().unwrap(), points[index].1);
+
+            cs.set("x/num", points[(index + 1) % 4].0);
+            assert_eq!(cs.which_is_unsatisfied(), Some("x-coordinate lookup"));
+        }
+    }
+
     #[test]
     fn test_lookup3_xy_with_conditional_negation() {
         let mut rng = XorShiftRng::from_seed([
@@ -287,6 +482,50 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_lookup() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x3d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        for (num_bits, table_len, num_constraints) in [(0, 1, 1), (1, 2, 1), (3, 8, 3), (3, 5, 3)] {
+            let table: Vec<Fr> = (0..table_len).map(|_| Fr::random(&mut rng)).collect();
+
+            for index in 0..(1 << num_bits) {
+                let mut cs = TestConstraintSystem::<Fr>::new();
+
+                let bits: Vec<Boolean> = (0..num_bits)
+                    .map(|i| {
+                        Boolean::from(
+                            AllocatedBit::alloc(
+                                cs.namespace(|| format!("bit {}", i)),
+                                Some((index >> i) & 1 == 1),
+                            )
+                            .unwrap(),
+                        )
+                    })
+                    .collect();
+
+                let res = lookup(cs.namespace(|| "lookup"), &bits, &table).unwrap();
+
+                assert!(cs.is_satisfied());
+                assert_eq!(
+                    res.get_value().unwrap(),
+                    table.get(index).copied().unwrap_or(Fr::ZERO)
+                );
+                assert_eq!(cs.num_constraints() - num_bits, num_constraints);
+            }
+        }
+
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let table = [Fr::ONE; 3];
+        assert!(matches!(
+            lookup(&mut cs, &[Boolean::constant(true)], &table),
+            Err(SynthesisError::IncompatibleLengthVector(_))
+        ));
+    }
+
     #[allow(clippy::needless_range_loop)]
     #[test]
     fn test_synth() {
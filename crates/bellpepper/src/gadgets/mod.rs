@@ -1,5 +1,6 @@
 //! Self-contained sub-circuit implementations for various primitives.
 pub mod blake2s;
+pub mod fixed_point;
 pub use bellpepper_core::boolean;
 #[macro_use]
 pub mod boolean_utils;
@@ -7,6 +8,7 @@ pub mod lookup;
 pub mod multieq;
 pub mod multipack;
 pub use bellpepper_core::num;
+pub mod num_builder;
 pub mod sha256;
 pub mod uint32;
 
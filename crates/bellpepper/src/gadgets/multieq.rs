@@ -104,6 +104,17 @@ impl<Scalar: PrimeField, CS: ConstraintSystem<Scalar>> ConstraintSystem<Scalar>
         self.cs.enforce(annotation, a, b, c)
     }
 
+    fn enforce_tagged<A, AR, LA, LB, LC>(&mut self, tag: &str, annotation: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+        LB: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+        LC: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+    {
+        self.cs.enforce_tagged(tag, annotation, a, b, c)
+    }
+
     fn push_namespace<NR, N>(&mut self, name_fn: N)
     where
         NR: Into<String>,
@@ -119,4 +130,17 @@ impl<Scalar: PrimeField, CS: ConstraintSystem<Scalar>> ConstraintSystem<Scalar>
     fn get_root(&mut self) -> &mut Self::Root {
         self
     }
+
+    fn num_constraints(&self) -> usize {
+        // Count the pending equalities, which are enforced on drop.
+        self.cs.num_constraints() + usize::from(self.bits_used > 0)
+    }
+
+    fn num_inputs(&self) -> usize {
+        self.cs.num_inputs()
+    }
+
+    fn num_aux(&self) -> usize {
+        self.cs.num_aux()
+    }
 }
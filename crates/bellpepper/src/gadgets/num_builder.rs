@@ -0,0 +1,231 @@
+//! Expression-style arithmetic over allocated numbers.
+//!
+//! A [`NumBuilder`] owns the constraint system for the duration of an
+//! expression, so that `+`, `-` and `*` on [`BuilderNum`]s can emit the same
+//! constraints as [`AllocatedNum::add`], [`AllocatedNum::sub`] and
+//! [`AllocatedNum::mul`], each under a fresh namespace.
+
+use std::cell::{Cell, RefCell};
+use std::io;
+use std::marker::PhantomData;
+use std::ops::{Add, Mul, Sub};
+use std::rc::Rc;
+
+use ff::PrimeField;
+
+use crate::gadgets::num::AllocatedNum;
+use bellpepper_core::{ConstraintSystem, Namespace, SynthesisError};
+
+/// Threads a constraint system through operator expressions on
+/// [`BuilderNum`]s.
+#[derive(Debug)]
+pub struct NumBuilder<Scalar: PrimeField, CS: ConstraintSystem<Scalar>> {
+    cs: RefCell<CS>,
+    count: Cell<usize>,
+    _e: PhantomData<Scalar>,
+}
+
+/// A number produced by a [`NumBuilder`]. Operators cannot return errors,
+/// so it holds the synthesis error which occurred while computing it
+/// instead, to be reported by [`BuilderNum::into_num`].
+#[derive(Debug)]
+pub struct BuilderNum<'a, Scalar: PrimeField, CS: ConstraintSystem<Scalar>> {
+    builder: &'a NumBuilder<Scalar, CS>,
+    num: Result<AllocatedNum<Scalar>, Rc<SynthesisError>>,
+}
+
+impl<Scalar: PrimeField, CS: ConstraintSystem<Scalar>> NumBuilder<Scalar, CS> {
+    pub fn new(cs: CS) -> Self {
+        NumBuilder {
+            cs: RefCell::new(cs),
+            count: Cell::new(0),
+            _e: PhantomData,
+        }
+    }
+
+    /// Allocates a new number in namespace `alloc {i}`.
+    pub fn alloc<F>(&self, value: F) -> BuilderNum<'_, Scalar, CS>
+    where
+        F: FnOnce() -> Result<Scalar, SynthesisError>,
+    {
+        self.synthesize("alloc", |cs| AllocatedNum::alloc(cs, value))
+    }
+
+    /// Wraps an already allocated number.
+    pub fn num(&self, num: &AllocatedNum<Scalar>) -> BuilderNum<'_, Scalar, CS> {
+        BuilderNum {
+            builder: self,
+            num: Ok(*num),
+        }
+    }
+
+    pub fn into_inner(self) -> CS {
+        self.cs.into_inner()
+    }
+
+    /// Runs `f` in a fresh namespace `{name} {i}`.
+    fn synthesize<F>(&self, name: &str, f: F) -> BuilderNum<'_, Scalar, CS>
+    where
+        F: FnOnce(Namespace<'_, Scalar, CS::Root>) -> Result<AllocatedNum<Scalar>, SynthesisError>,
+    {
+        let i = self.count.get();
+        self.count.set(i + 1);
+
+        let mut cs = self.cs.borrow_mut();
+        let num = f(cs.namespace(|| format!("{name} {i}"))).map_err(Rc::new);
+
+        BuilderNum { builder: self, num }
+    }
+}
+
+impl<'a, Scalar: PrimeField, CS: ConstraintSystem<Scalar>> Clone for BuilderNum<'a, Scalar, CS> {
+    fn clone(&self) -> Self {
+        BuilderNum {
+            builder: self.builder,
+            num: self.num.clone(),
+        }
+    }
+}
+
+impl<'a, Scalar: PrimeField, CS: ConstraintSystem<Scalar>> BuilderNum<'a, Scalar, CS> {
+    pub fn get_value(&self) -> Option<Scalar> {
+        self.num.as_ref().ok().and_then(|num| num.get_value())
+    }
+
+    /// Returns the underlying number, or the first error which occurred
+    /// while synthesizing it.
+    pub fn into_num(self) -> Result<AllocatedNum<Scalar>, SynthesisError> {
+        self.num
+            .map_err(|e| Rc::try_unwrap(e).unwrap_or_else(|e| clone_error(&e)))
+    }
+
+    fn binary_op<F>(self, other: Self, name: &str, f: F) -> Self
+    where
+        F: FnOnce(
+            &AllocatedNum<Scalar>,
+            Namespace<'_, Scalar, CS::Root>,
+            &AllocatedNum<Scalar>,
+        ) -> Result<AllocatedNum<Scalar>, SynthesisError>,
+    {
+        assert!(
+            std::ptr::eq(self.builder, other.builder),
+            "operands belong to different builders"
+        );
+
+        match (self.num, other.num) {
+            (Ok(a), Ok(b)) => self.builder.synthesize(name, |cs| f(&a, cs, &b)),
+            (Err(e), _) | (_, Err(e)) => BuilderNum {
+                builder: self.builder,
+                num: Err(e),
+            },
+        }
+    }
+}
+
+/// Copies an error shared by several numbers. `SynthesisError` is not
+/// `Clone`, as `io::Error` is not.
+fn clone_error(e: &SynthesisError) -> SynthesisError {
+    match e {
+        SynthesisError::AssignmentMissing => SynthesisError::AssignmentMissing,
+        SynthesisError::DivisionByZero => SynthesisError::DivisionByZero,
+        SynthesisError::Unsatisfiable => SynthesisError::Unsatisfiable,
+        SynthesisError::PolynomialDegreeTooLarge => SynthesisError::PolynomialDegreeTooLarge,
+        SynthesisError::UnexpectedIdentity => SynthesisError::UnexpectedIdentity,
+        SynthesisError::IoError(e) => {
+            SynthesisError::IoError(io::Error::new(e.kind(), e.to_string()))
+        }
+        SynthesisError::MalformedVerifyingKey => SynthesisError::MalformedVerifyingKey,
+        SynthesisError::UnconstrainedVariable => SynthesisError::UnconstrainedVariable,
+        SynthesisError::MalformedProofs(s) => SynthesisError::MalformedProofs(s.clone()),
+        SynthesisError::MalformedSrs => SynthesisError::MalformedSrs,
+        SynthesisError::NonPowerOfTwo => SynthesisError::NonPowerOfTwo,
+        SynthesisError::IncompatibleLengthVector(s) => {
+            SynthesisError::IncompatibleLengthVector(s.clone())
+        }
+        SynthesisError::InvalidPairing => SynthesisError::InvalidPairing,
+    }
+}
+
+impl<'a, Scalar: PrimeField, CS: ConstraintSystem<Scalar>> Add for BuilderNum<'a, Scalar, CS> {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        self.binary_op(other, "add", |a, cs, b| a.add(cs, b))
+    }
+}
+
+impl<'a, Scalar: PrimeField, CS: ConstraintSystem<Scalar>> Sub for BuilderNum<'a, Scalar, CS> {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        self.binary_op(other, "sub", |a, cs, b| a.sub(cs, b))
+    }
+}
+
+impl<'a, Scalar: PrimeField, CS: ConstraintSystem<Scalar>> Mul for BuilderNum<'a, Scalar, CS> {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        self.binary_op(other, "mul", |a, cs, b| a.mul(cs, b))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bellpepper_core::test_cs::*;
+    use blstrs::Scalar as Fr;
+
+    #[test]
+    fn test_num_builder() {
+        // (a + b) * a - b, with a = 3 and b = 4.
+        let mut explicit = TestConstraintSystem::<Fr>::new();
+        let a = AllocatedNum::alloc(explicit.namespace(|| "a"), || Ok(Fr::from(3u64))).unwrap();
+        let b = AllocatedNum::alloc(explicit.namespace(|| "b"), || Ok(Fr::from(4u64))).unwrap();
+        let sum = a.add(explicit.namespace(|| "a + b"), &b).unwrap();
+        let product = sum.mul(explicit.namespace(|| "(a + b) * a"), &a).unwrap();
+        let expected = product
+            .sub(explicit.namespace(|| "(a + b) * a - b"), &b)
+            .unwrap();
+
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let builder = NumBuilder::new(&mut cs);
+        let a = builder.alloc(|| Ok(Fr::from(3u64)));
+        let b = builder.alloc(|| Ok(Fr::from(4u64)));
+        let out = (a.clone() + b.clone()) * a - b;
+        let out = out.into_num().unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(out.get_value(), Some(Fr::from(17u64)));
+        assert_eq!(out.get_value(), expected.get_value());
+        assert_eq!(cs.num_constraints(), explicit.num_constraints());
+        assert_eq!(cs.num_inputs(), explicit.num_inputs());
+        assert_eq!(cs.get("mul 3/product num"), Fr::from(21u64));
+    }
+
+    #[test]
+    fn test_num_builder_error() {
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let builder = NumBuilder::new(&mut cs);
+        let a = builder.alloc(|| Err(SynthesisError::AssignmentMissing));
+        let b = builder.alloc(|| Ok(Fr::from(4u64)));
+        let c = builder.alloc(|| Err(SynthesisError::DivisionByZero));
+
+        let out = a * b.clone() + b.clone();
+        assert!(matches!(
+            out.clone().into_num(),
+            Err(SynthesisError::AssignmentMissing)
+        ));
+        assert!(matches!(
+            out.into_num(),
+            Err(SynthesisError::AssignmentMissing)
+        ));
+
+        // Each number reports the error it depends on.
+        assert!(matches!(
+            (b.clone() - c).into_num(),
+            Err(SynthesisError::DivisionByZero)
+        ));
+        assert!(b.into_num().is_ok());
+    }
+}
@@ -1,3 +1,5 @@
 #![deny(missing_debug_implementations)]
+#[cfg(feature = "bellman")]
+pub mod bellman_adaptor;
 pub mod gadgets;
 pub mod util_cs;
@@ -98,4 +98,16 @@ impl<Scalar: PrimeField> ConstraintSystem<Scalar> for BenchCS<Scalar> {
     fn get_root(&mut self) -> &mut Self::Root {
         self
     }
+
+    fn num_constraints(&self) -> usize {
+        self.a
+    }
+
+    fn num_inputs(&self) -> usize {
+        self.inputs
+    }
+
+    fn num_aux(&self) -> usize {
+        self.aux
+    }
 }
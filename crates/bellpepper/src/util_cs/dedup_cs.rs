@@ -0,0 +1,201 @@
+use std::collections::HashSet;
+use std::marker::PhantomData;
+
+use ff::PrimeField;
+
+use bellpepper_core::{ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
+
+/// A linear combination reduced to its nonzero `(index, coefficient)` terms,
+/// with coefficients in their canonical byte representation.
+type LcKey = Vec<(Index, Vec<u8>)>;
+
+/// Wraps a constraint system and drops every constraint identical to one
+/// already enforced. Two constraints are identical when their `A`, `B` and
+/// `C` linear combinations have the same nonzero terms, so dropping a
+/// duplicate never changes which assignments are satisfying.
+#[derive(Debug)]
+pub struct DedupCS<Scalar: PrimeField, CS: ConstraintSystem<Scalar>> {
+    cs: CS,
+    seen: HashSet<(LcKey, LcKey, LcKey)>,
+    duplicates: usize,
+    _e: PhantomData<Scalar>,
+}
+
+impl<Scalar: PrimeField, CS: ConstraintSystem<Scalar>> DedupCS<Scalar, CS> {
+    pub fn new(cs: CS) -> Self {
+        DedupCS {
+            cs,
+            seen: HashSet::new(),
+            duplicates: 0,
+            _e: PhantomData,
+        }
+    }
+
+    /// Returns the number of duplicate constraints dropped so far.
+    pub fn num_duplicates(&self) -> usize {
+        self.duplicates
+    }
+
+    pub fn into_inner(self) -> CS {
+        self.cs
+    }
+
+    /// Records the constraint `a * b = c`, returning whether an identical
+    /// one was already enforced.
+    fn is_duplicate(
+        &mut self,
+        a: &LinearCombination<Scalar>,
+        b: &LinearCombination<Scalar>,
+        c: &LinearCombination<Scalar>,
+    ) -> bool {
+        if self.seen.insert((lc_key(a), lc_key(b), lc_key(c))) {
+            return false;
+        }
+
+        self.duplicates += 1;
+        true
+    }
+}
+
+fn lc_key<Scalar: PrimeField>(lc: &LinearCombination<Scalar>) -> LcKey {
+    lc.iter()
+        .filter(|(_, coeff)| !bool::from(coeff.is_zero()))
+        .map(|(var, coeff)| (var.get_unchecked(), coeff.to_repr().as_ref().to_vec()))
+        .collect()
+}
+
+impl<Scalar: PrimeField, CS: ConstraintSystem<Scalar>> ConstraintSystem<Scalar>
+    for DedupCS<Scalar, CS>
+{
+    type Root = Self;
+
+    fn one() -> Variable {
+        CS::one()
+    }
+
+    fn alloc<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<Scalar, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.cs.alloc(annotation, f)
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<Scalar, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.cs.alloc_input(annotation, f)
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, annotation: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+        LB: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+        LC: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+    {
+        let a = a(LinearCombination::zero());
+        let b = b(LinearCombination::zero());
+        let c = c(LinearCombination::zero());
+
+        if self.is_duplicate(&a, &b, &c) {
+            return;
+        }
+
+        self.cs.enforce(annotation, |_| a, |_| b, |_| c)
+    }
+
+    fn enforce_tagged<A, AR, LA, LB, LC>(&mut self, tag: &str, annotation: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+        LB: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+        LC: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+    {
+        let a = a(LinearCombination::zero());
+        let b = b(LinearCombination::zero());
+        let c = c(LinearCombination::zero());
+
+        if self.is_duplicate(&a, &b, &c) {
+            return;
+        }
+
+        self.cs.enforce_tagged(tag, annotation, |_| a, |_| b, |_| c)
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.cs.get_root().push_namespace(name_fn)
+    }
+
+    fn pop_namespace(&mut self) {
+        self.cs.get_root().pop_namespace()
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+
+    fn num_constraints(&self) -> usize {
+        self.cs.num_constraints()
+    }
+
+    fn num_inputs(&self) -> usize {
+        self.cs.num_inputs()
+    }
+
+    fn num_aux(&self) -> usize {
+        self.cs.num_aux()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gadgets::num::AllocatedNum;
+    use bellpepper_core::test_cs::TestConstraintSystem;
+    use blstrs::Scalar as Fr;
+    use ff::Field;
+
+    fn synthesize<CS: ConstraintSystem<Fr>>(mut cs: CS) {
+        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::ONE)).unwrap();
+        let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::from(2u64))).unwrap();
+
+        // Two gadgets independently checking that `a` is boolean.
+        a.enforce_boolean(cs.namespace(|| "first check")).unwrap();
+        a.enforce_boolean(cs.namespace(|| "second check")).unwrap();
+        a.mul(cs.namespace(|| "a * b"), &b).unwrap();
+    }
+
+    #[test]
+    fn test_dedup_cs() {
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        synthesize(&mut cs);
+        assert!(cs.is_satisfied());
+        assert_eq!(cs.num_constraints(), 3);
+
+        let mut deduped = TestConstraintSystem::<Fr>::new();
+        let mut dedup = DedupCS::new(&mut deduped);
+        synthesize(&mut dedup);
+        assert_eq!(dedup.num_duplicates(), 1);
+        drop(dedup);
+
+        assert!(deduped.is_satisfied());
+        assert_eq!(deduped.num_constraints(), 2);
+
+        deduped.set("a/num", Fr::from(2u64));
+        assert_eq!(
+            deduped.which_is_unsatisfied(),
+            Some("first check/boolean constraint")
+        );
+    }
+}
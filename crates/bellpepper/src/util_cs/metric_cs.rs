@@ -283,6 +283,18 @@ impl<Scalar: PrimeField> ConstraintSystem<Scalar> for MetricCS<Scalar> {
     fn get_root(&mut self) -> &mut Self::Root {
         self
     }
+
+    fn num_constraints(&self) -> usize {
+        self.constraints.len()
+    }
+
+    fn num_inputs(&self) -> usize {
+        self.inputs.len()
+    }
+
+    fn num_aux(&self) -> usize {
+        self.aux.len()
+    }
 }
 
 fn compute_path(ns: &[String], this: &str) -> String {
@@ -305,3 +317,20 @@ fn compute_path(ns: &[String], this: &str) -> String {
 
     name
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gadgets::num::AllocatedNum;
+    use blstrs::Scalar as Fr;
+
+    #[test]
+    fn test_alloc_value_without_assignment() {
+        let mut cs = MetricCS::<Fr>::new();
+
+        // A metric pass never asks for assignments, so a missing value is fine.
+        let n = AllocatedNum::alloc_value(cs.namespace(|| "a"), None).unwrap();
+        assert_eq!(n.get_value(), None);
+        assert_eq!(cs.aux(), vec!["a/num".to_string()]);
+    }
+}
@@ -1,5 +1,58 @@
 pub mod bench_cs;
+pub mod dedup_cs;
 pub mod metric_cs;
+pub mod replay_cs;
 pub mod witness_cs;
 
-pub use bellpepper_core::{Comparable, Constraint};
+pub use bellpepper_core::{CircuitShape, Comparable, Constraint};
+
+#[cfg(test)]
+mod tests {
+    use super::bench_cs::BenchCS;
+    use super::metric_cs::MetricCS;
+    use super::witness_cs::WitnessCS;
+    use crate::gadgets::num::AllocatedNum;
+    use bellpepper_core::test_cs::TestConstraintSystem;
+    use bellpepper_core::ConstraintSystem;
+    use blstrs::Scalar as Fr;
+    use ff::Field;
+
+    /// Synthesizes `a * b` and a public copy of the product, checking the
+    /// counts through the trait alone. Witness generators do not record
+    /// constraints, so they report none.
+    fn synthesize_and_count<CS: ConstraintSystem<Fr>>(mut cs: CS) {
+        let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fr::ONE)).unwrap();
+        let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::ONE)).unwrap();
+
+        let mut ns = cs.namespace(|| "product");
+        let c = a.mul(&mut ns, &b).unwrap();
+        let expected = usize::from(!ns.is_witness_generator());
+        assert_eq!(ns.num_constraints(), expected);
+        c.inputize(&mut ns).unwrap();
+        drop(ns);
+
+        assert_eq!(cs.num_inputs(), 2);
+        assert_eq!(cs.num_aux(), 3);
+    }
+
+    #[test]
+    fn test_constraint_system_counts() {
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        synthesize_and_count(&mut cs);
+        assert_eq!(cs.num_constraints(), 2);
+
+        let mut cs = MetricCS::<Fr>::new();
+        synthesize_and_count(&mut cs);
+        assert_eq!(cs.num_constraints(), 2);
+
+        let mut cs = BenchCS::<Fr>::new();
+        synthesize_and_count(&mut cs);
+        assert_eq!(cs.num_constraints(), 2);
+
+        // Witness generators count inputs and aux from their slices.
+        let mut cs = WitnessCS::<Fr>::new();
+        synthesize_and_count(&mut cs);
+        assert_eq!(cs.aux_assignment().len(), 3);
+        assert_eq!(cs.num_constraints(), 0);
+    }
+}
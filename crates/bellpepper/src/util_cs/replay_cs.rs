@@ -0,0 +1,321 @@
+//! Record the witness of one synthesis pass and replay it in later passes
+//! without re-running the value closures.
+
+use ff::PrimeField;
+
+use bellpepper_core::{ConstraintSystem, LinearCombination, SynthesisError, Variable};
+
+/// Wraps a constraint system and records the value of every allocated
+/// variable, inputs and auxiliaries alike, in allocation order. Every value
+/// closure is evaluated eagerly, so the whole witness must be known.
+#[derive(Debug)]
+pub struct RecordingCS<Scalar: PrimeField, CS: ConstraintSystem<Scalar>> {
+    cs: CS,
+    assignments: Vec<(Variable, Scalar)>,
+}
+
+impl<Scalar: PrimeField, CS: ConstraintSystem<Scalar>> RecordingCS<Scalar, CS> {
+    pub fn new(cs: CS) -> Self {
+        RecordingCS {
+            cs,
+            assignments: vec![],
+        }
+    }
+
+    pub fn assignments(&self) -> &[(Variable, Scalar)] {
+        &self.assignments
+    }
+
+    /// Returns the recorded assignments, to be fed to [`ReplayCS::new`].
+    pub fn into_assignments(self) -> Vec<(Variable, Scalar)> {
+        self.assignments
+    }
+}
+
+impl<Scalar: PrimeField, CS: ConstraintSystem<Scalar>> ConstraintSystem<Scalar>
+    for RecordingCS<Scalar, CS>
+{
+    type Root = Self;
+
+    fn one() -> Variable {
+        CS::one()
+    }
+
+    fn alloc<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<Scalar, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let value = f()?;
+        let var = self.cs.alloc(annotation, || Ok(value))?;
+        self.assignments.push((var, value));
+        Ok(var)
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<Scalar, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let value = f()?;
+        let var = self.cs.alloc_input(annotation, || Ok(value))?;
+        self.assignments.push((var, value));
+        Ok(var)
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, annotation: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+        LB: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+        LC: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+    {
+        self.cs.enforce(annotation, a, b, c)
+    }
+
+    fn enforce_tagged<A, AR, LA, LB, LC>(&mut self, tag: &str, annotation: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+        LB: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+        LC: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+    {
+        self.cs.enforce_tagged(tag, annotation, a, b, c)
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.cs.get_root().push_namespace(name_fn)
+    }
+
+    fn pop_namespace(&mut self) {
+        self.cs.get_root().pop_namespace()
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+
+    fn num_constraints(&self) -> usize {
+        self.cs.num_constraints()
+    }
+
+    fn num_inputs(&self) -> usize {
+        self.cs.num_inputs()
+    }
+
+    fn num_aux(&self) -> usize {
+        self.cs.num_aux()
+    }
+}
+
+/// Wraps a constraint system and assigns each allocated variable the next
+/// value recorded by a [`RecordingCS`], ignoring the value closures.
+///
+/// Synthesis must allocate variables in the same order as the recorded pass.
+/// Allocating a variable whose index differs from the recorded one returns
+/// [`SynthesisError::Unsatisfiable`]; allocating more variables than were
+/// recorded returns [`SynthesisError::AssignmentMissing`].
+#[derive(Debug)]
+pub struct ReplayCS<Scalar: PrimeField, CS: ConstraintSystem<Scalar>> {
+    cs: CS,
+    assignments: Vec<(Variable, Scalar)>,
+    next: usize,
+}
+
+impl<Scalar: PrimeField, CS: ConstraintSystem<Scalar>> ReplayCS<Scalar, CS> {
+    pub fn new(cs: CS, assignments: Vec<(Variable, Scalar)>) -> Self {
+        ReplayCS {
+            cs,
+            assignments,
+            next: 0,
+        }
+    }
+
+    /// Returns the number of recorded assignments not yet replayed.
+    pub fn remaining(&self) -> usize {
+        self.assignments.len() - self.next
+    }
+
+    pub fn into_inner(self) -> CS {
+        self.cs
+    }
+
+    fn next_assignment(&mut self) -> Result<(Variable, Scalar), SynthesisError> {
+        let assignment = *self
+            .assignments
+            .get(self.next)
+            .ok_or(SynthesisError::AssignmentMissing)?;
+        self.next += 1;
+        Ok(assignment)
+    }
+}
+
+impl<Scalar: PrimeField, CS: ConstraintSystem<Scalar>> ConstraintSystem<Scalar>
+    for ReplayCS<Scalar, CS>
+{
+    type Root = Self;
+
+    fn one() -> Variable {
+        CS::one()
+    }
+
+    fn alloc<F, A, AR>(&mut self, annotation: A, _f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<Scalar, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let (expected, value) = self.next_assignment()?;
+        let var = self.cs.alloc(annotation, || Ok(value))?;
+        if var != expected {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+        Ok(var)
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, annotation: A, _f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<Scalar, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let (expected, value) = self.next_assignment()?;
+        let var = self.cs.alloc_input(annotation, || Ok(value))?;
+        if var != expected {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+        Ok(var)
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, annotation: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+        LB: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+        LC: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+    {
+        self.cs.enforce(annotation, a, b, c)
+    }
+
+    fn enforce_tagged<A, AR, LA, LB, LC>(&mut self, tag: &str, annotation: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+        LB: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+        LC: FnOnce(LinearCombination<Scalar>) -> LinearCombination<Scalar>,
+    {
+        self.cs.enforce_tagged(tag, annotation, a, b, c)
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.cs.get_root().push_namespace(name_fn)
+    }
+
+    fn pop_namespace(&mut self) {
+        self.cs.get_root().pop_namespace()
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+
+    fn num_constraints(&self) -> usize {
+        self.cs.num_constraints()
+    }
+
+    fn num_inputs(&self) -> usize {
+        self.cs.num_inputs()
+    }
+
+    fn num_aux(&self) -> usize {
+        self.cs.num_aux()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gadgets::num::AllocatedNum;
+    use bellpepper_core::test_cs::TestConstraintSystem;
+    use blstrs::Scalar as Fr;
+
+    fn synthesize<CS: ConstraintSystem<Fr>>(mut cs: CS, x: Option<u64>) {
+        let value = || x.map(Fr::from).ok_or(SynthesisError::AssignmentMissing);
+        let a = AllocatedNum::alloc_input(cs.namespace(|| "a"), value).unwrap();
+        let b = a.square(cs.namespace(|| "a^2")).unwrap();
+        let c = b.mul(cs.namespace(|| "a^3"), &a).unwrap();
+        c.inputize(cs.namespace(|| "c")).unwrap();
+    }
+
+    #[test]
+    fn test_record_replay() {
+        let mut recorded = TestConstraintSystem::<Fr>::new();
+        let mut recording = RecordingCS::new(&mut recorded);
+        synthesize(&mut recording, Some(3));
+        let assignments = recording.into_assignments();
+
+        assert!(recorded.is_satisfied());
+        assert_eq!(assignments.len(), 4);
+        assert_eq!(assignments[3].1, Fr::from(27u64));
+
+        // Replaying needs no witness values at all.
+        let mut replayed = TestConstraintSystem::<Fr>::new();
+        let mut replay = ReplayCS::new(&mut replayed, assignments.clone());
+        synthesize(&mut replay, None);
+        assert_eq!(replay.remaining(), 0);
+
+        assert!(replayed.is_satisfied());
+        assert_eq!(replayed.num_constraints(), recorded.num_constraints());
+        assert_eq!(replayed.get("a^3/product num"), Fr::from(27u64));
+
+        // A tampered recording is replayed faithfully, and fails to verify.
+        let mut tampered = assignments;
+        tampered[1].1 = Fr::from(10u64);
+        let mut replayed = TestConstraintSystem::<Fr>::new();
+        synthesize(ReplayCS::new(&mut replayed, tampered), None);
+        assert_eq!(
+            replayed.which_is_unsatisfied(),
+            Some("a^2/squaring constraint")
+        );
+    }
+
+    #[test]
+    fn test_replay_exhausted() {
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let mut replay = ReplayCS::new(&mut cs, vec![]);
+        assert!(matches!(
+            AllocatedNum::alloc(&mut replay, || Ok(Fr::from(1u64))),
+            Err(SynthesisError::AssignmentMissing)
+        ));
+    }
+
+    #[test]
+    fn test_replay_diverged() {
+        let mut recorded = TestConstraintSystem::<Fr>::new();
+        let mut recording = RecordingCS::new(&mut recorded);
+        AllocatedNum::alloc_input(&mut recording, || Ok(Fr::from(1u64))).unwrap();
+        let assignments = recording.into_assignments();
+
+        // The recorded input is replayed as an auxiliary variable.
+        let mut cs = TestConstraintSystem::<Fr>::new();
+        let mut replay = ReplayCS::new(&mut cs, assignments);
+        assert!(matches!(
+            AllocatedNum::alloc(&mut replay, || Ok(Fr::from(1u64))),
+            Err(SynthesisError::Unsatisfiable)
+        ));
+    }
+}
@@ -83,6 +83,42 @@ where
     pub fn to_assignments(self) -> (Vec<Scalar>, Vec<Scalar>) {
         (self.input_assignment, self.aux_assignment)
     }
+
+    /// Allocates one private variable per closure in `fs`, in order. With the
+    /// `rayon` feature the closures are evaluated in parallel, so they should
+    /// be independent of each other; the variables are appended only once
+    /// every value is known, so their indices match serial allocation.
+    pub fn alloc_par<F>(&mut self, fs: Vec<F>) -> Result<Vec<Variable>, SynthesisError>
+    where
+        F: FnOnce() -> Result<Scalar, SynthesisError> + Send,
+    {
+        #[cfg(feature = "rayon")]
+        let values = {
+            use rayon::prelude::*;
+
+            fs.into_par_iter()
+                .map(|f| f())
+                .collect::<Result<Vec<_>, _>>()?
+        };
+        #[cfg(not(feature = "rayon"))]
+        let values = fs.into_iter().map(|f| f()).collect::<Result<Vec<_>, _>>()?;
+
+        let start = self.aux_assignment.len();
+        self.aux_assignment.extend(values);
+
+        Ok((start..self.aux_assignment.len())
+            .map(|i| Variable(Index::Aux(i)))
+            .collect())
+    }
+
+    /// Returns the value assigned to `var`, or `None` if it has not been
+    /// allocated in this constraint system.
+    pub fn get_value(&self, var: Variable) -> Option<Scalar> {
+        match var.get_unchecked() {
+            Index::Input(i) => self.input_assignment.get(i).copied(),
+            Index::Aux(i) => self.aux_assignment.get(i).copied(),
+        }
+    }
 }
 
 impl<Scalar> ConstraintSystem<Scalar> for WitnessCS<Scalar>
@@ -218,3 +254,66 @@ impl<Scalar: PrimeField> WitnessCS<Scalar> {
         self.aux_assignment.clone()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bellpepper_core::num::AllocatedNum;
+    use blstrs::Scalar as Fr;
+    use ff::Field;
+
+    #[test]
+    fn test_witness_cs_get_value() {
+        let mut cs = WitnessCS::<Fr>::new();
+
+        let a = AllocatedNum::alloc_input(cs.namespace(|| "a"), || Ok(Fr::from(3u64))).unwrap();
+        let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fr::from(5u64))).unwrap();
+        let c = a.mul(cs.namespace(|| "a * b"), &b).unwrap();
+        let d = c.add(cs.namespace(|| "c + a"), &a).unwrap();
+
+        assert_eq!(cs.get_value(WitnessCS::<Fr>::one()), Some(Fr::ONE));
+        assert_eq!(cs.get_value(a.get_variable()), Some(Fr::from(3u64)));
+        assert_eq!(cs.get_value(c.get_variable()), Some(Fr::from(15u64)));
+        assert_eq!(cs.get_value(d.get_variable()), Some(Fr::from(18u64)));
+        assert_eq!(
+            cs.get_value(Variable::new_unchecked(Index::Aux(
+                cs.aux_assignment().len()
+            ))),
+            None
+        );
+    }
+
+    #[test]
+    fn test_witness_cs_alloc_par() {
+        let value = |i: u64| Fr::from(i).square() + Fr::from(i);
+
+        let mut serial = WitnessCS::<Fr>::new();
+        let a = serial.alloc(|| "a", || Ok(Fr::from(7u64))).unwrap();
+        let serial_vars = (0..64u64)
+            .map(|i| serial.alloc(|| format!("{}", i), || Ok(value(i))).unwrap())
+            .collect::<Vec<_>>();
+
+        let mut parallel = WitnessCS::<Fr>::new();
+        parallel.alloc(|| "a", || Ok(Fr::from(7u64))).unwrap();
+        let parallel_vars = parallel
+            .alloc_par((0..64u64).map(|i| move || Ok(value(i))).collect())
+            .unwrap();
+
+        assert_eq!(serial_vars, parallel_vars);
+        assert_eq!(serial, parallel);
+
+        let b = parallel.alloc(|| "b", || Ok(Fr::ONE)).unwrap();
+        assert_eq!(parallel.get_value(b), Some(Fr::ONE));
+        assert_eq!(parallel.get_value(a), Some(Fr::from(7u64)));
+
+        let failing: Vec<Box<dyn FnOnce() -> Result<Fr, SynthesisError> + Send>> = vec![
+            Box::new(|| Ok(Fr::ONE)),
+            Box::new(|| Err(SynthesisError::AssignmentMissing)),
+        ];
+        assert!(matches!(
+            parallel.alloc_par(failing),
+            Err(SynthesisError::AssignmentMissing)
+        ));
+        assert_eq!(parallel.aux_assignment().len(), 66);
+    }
+}